@@ -73,6 +73,12 @@ var initBaseInterval = setInterval(function () {
 
 // FUNCTIONS ----------------------------------------------------------
 
+// Settings of the profile being mined with.
+function activeProfile() {
+    let config = window.state.config;
+    return config.profiles[config.activeProfile];
+}
+
 // Navigate to specified page.
 function navigate(newPage) {
     for (let oldPage of pages) {
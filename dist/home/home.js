@@ -20,8 +20,13 @@ var initHomeInterval = setInterval(function () {
 
         // Start mining.
         document.getElementById("start-mining").addEventListener("click", () => {
+            startMining();
+        })
+
+        // Stop mining.
+        document.getElementById("stop-mining").addEventListener("click", () => {
             window.__TAURI__
-                .invoke('start_mining');
+                .invoke('stop_mining');
         })
 
         // Pause mining.
@@ -36,6 +41,14 @@ var initHomeInterval = setInterval(function () {
                 .invoke('resume_mining');
         })
 
+        window.__TAURI__
+            .invoke('startup_phase')
+            .then(phase => updateStartupPhase(phase));
+
+        window.__TAURI__
+            .invoke('schedule_status')
+            .then(status => updateScheduleStatus(status));
+
         clearTimeout(initHomeInterval);
     }
 }, 100);
@@ -44,7 +57,30 @@ var initHomeInterval = setInterval(function () {
 
 function startMining() {
     window.__TAURI__
-        .invoke('start_mining');
+        .invoke('start_mining')
+        .then(report => showPreflightReport(report, null))
+        .catch(e => showPreflightReport(e.preflight, e.message));
+}
+
+// List the pre-flight checks that didn't pass, or why mining couldn't start otherwise.
+function showPreflightReport(report, message) {
+    let list = document.getElementById("preflight-report");
+    list.innerHTML = "";
+    let problems = report?.checks.filter(check => check.severity != "pass") ?? [];
+    if (problems.length == 0 && message) {
+        problems = [{ severity: "error", message: message }];
+    }
+    for (let check of problems) {
+        let item = document.createElement("li");
+        item.className = "preflight-" + check.severity;
+        item.innerText = check.message;
+        list.appendChild(item);
+    }
+}
+
+function stopMining() {
+    window.__TAURI__
+        .invoke('stop_mining');
 }
 
 function pauseMining() {
@@ -57,9 +93,7 @@ function resumeMining() {
         .invoke('resume_mining');
 }
 
-function updateStatus(status) {
-    let summary = JSON.parse(status);
-
+function updateStatus(summary) {
     // Display hashrate.
     if (summary.hashrate.total[0] !== null) {
         document.getElementById("hashrate-10s").innerText = summary.hashrate.total[0].toFixed(0) + " H/s";
@@ -68,14 +102,118 @@ function updateStatus(status) {
     }
 }
 
+function updateP2poolStatus(status) {
+    let text = status.sharesFound + " found, " + status.currentEffort.toFixed(0) + "% effort";
+    text += " \u2014 ~" + (status.estimatedPayout / 1e12).toFixed(6) + " XMR per block";
+    document.getElementById("p2pool-shares").innerText = text;
+}
+
+function updateSyncProgress(progress) {
+    let text = progress.percent.toFixed(0) + "%";
+    if (progress.height >= progress.targetHeight) {
+        text = "Synchronized";
+    } else if (progress.etaSecs !== null) {
+        text = "Synchronizing " + text + " \u2014 ~" + formatDuration(progress.etaSecs) + " remaining";
+    } else {
+        text = "Synchronizing " + text;
+    }
+    document.getElementById("sync-progress").innerText = text;
+}
+
+function formatDuration(secs) {
+    if (secs >= 86400) {
+        return Math.round(secs / 86400) + "d";
+    } else if (secs >= 3600) {
+        return Math.round(secs / 3600) + "h";
+    } else if (secs >= 60) {
+        return Math.round(secs / 60) + "m";
+    }
+    return secs + "s";
+}
+
+function updateProcessState(processState) {
+    let text;
+    switch (processState.state) {
+        case "running":
+            text = "Running";
+            break;
+        case "crashed":
+            text = "Crashed, restarting in " + processState.restartInSecs + "s";
+            break;
+        case "failed":
+            text = "Crashed " + processState.crashes + " times, not restarting";
+            break;
+        default:
+            text = "Stopped";
+    }
+    document.getElementById(processState.component + "-state").innerText = text;
+}
+
+function updateStartupPhase(phase) {
+    let text;
+    switch (phase.phase) {
+        case "startingMonerod":
+            text = "Starting Monerod";
+            break;
+        case "waitingForSync":
+            text = "Waiting for the blockchain to sync";
+            break;
+        case "startingP2pool":
+            text = "Starting P2Pool";
+            break;
+        case "waitingForP2pool":
+            text = "Waiting for P2Pool";
+            break;
+        case "startingXmrig":
+            text = "Starting XMRig";
+            break;
+        case "mining":
+            text = "Mining";
+            break;
+        case "failed":
+            text = "Failed: " + phase.message;
+            break;
+        default:
+            text = "Stopped";
+    }
+    document.getElementById("startup-phase").innerText = text;
+}
+
+function updateScheduleStatus(status) {
+    let text = "Off";
+    if (status.enabled) {
+        text = status.mining ? "Mining" : "Paused";
+        if (status.nextTransition !== null) {
+            let next = new Date(status.nextTransition * 1000);
+            text += " until " + next.toLocaleString(undefined, {
+                weekday: "short",
+                hour: "numeric",
+                minute: "2-digit",
+            });
+        }
+        if (status.overridden) {
+            text += " (overridden)";
+        }
+    }
+    document.getElementById("schedule-status").innerText = text;
+}
+
+function updateActivePool(pool) {
+    let text = pool.url;
+    if (pool.failover) {
+        text += " (failover)";
+    }
+    document.getElementById("active-pool").innerText = text;
+}
+
 function setupComplete() {
-    let config = window.state.config;
-    if (config.pool) {
-        if (config.pool.local) {
-            if (config.pool.local.moneroAddress) {
+    let pool = activeProfile()?.pool;
+    if (pool) {
+        if (pool.local) {
+            if (pool.local.moneroAddress) {
                 return true
             }
-        } else if (config.pool.remote) {
+        } else if (pool.remote) {
             return true
         }
     }
@@ -87,3 +225,27 @@ function setupComplete() {
 window.__TAURI__.event.listen('xmrig-status', (event) => {
     updateStatus(event.payload);
 })
+
+window.__TAURI__.event.listen('active-pool', (event) => {
+    updateActivePool(event.payload);
+})
+
+window.__TAURI__.event.listen('p2pool-status', (event) => {
+    updateP2poolStatus(event.payload);
+})
+
+window.__TAURI__.event.listen('sync-progress', (event) => {
+    updateSyncProgress(event.payload);
+})
+
+window.__TAURI__.event.listen('process-state', (event) => {
+    updateProcessState(event.payload);
+})
+
+window.__TAURI__.event.listen('startup-phase', (event) => {
+    updateStartupPhase(event.payload);
+})
+
+window.__TAURI__.event.listen('schedule-status', (event) => {
+    updateScheduleStatus(event.payload);
+})
@@ -1,7 +1,7 @@
 var initWelcomeInterval = setInterval(function () {
     if (window.state.pagesLoaded) {
 
-        document.getElementById("setup-blockchain-dir").value = window.state.config.pool.local.daemon.local.blockchainDir;
+        document.getElementById("setup-blockchain-dir").value = activeProfile().pool.local?.daemon.local?.blockchainDir ?? "";
 
         // LISTENERS ----------------------------------------------------------
 
@@ -14,6 +14,22 @@ var initWelcomeInterval = setInterval(function () {
 
         // Next setting.
         document.getElementById("next-setting").addEventListener("click", () => {
+            if (window.state.setupStep == 0) {
+                // Check the address before moving on, so that mistakes are caught early.
+                let moneroAddress = document.getElementById("setup-monero-address").value;
+                window.__TAURI__
+                    .invoke('validate_address', { address: moneroAddress })
+                    .then(_ => {
+                        document.getElementById("setup-address-error").innerText = "";
+                        nextStep();
+                    })
+                    .catch(e => document.getElementById("setup-address-error").innerText = e.message);
+            } else {
+                nextStep();
+            }
+        })
+
+        function nextStep() {
             document.getElementById("next-setting").disabled = true;
             let oldStep = "setup-step-" + window.state.setupStep;
             window.state.setupStep += 1;
@@ -25,7 +41,7 @@ var initWelcomeInterval = setInterval(function () {
                 document.getElementById("next-setting").style.display = "none";
                 document.getElementById("done-setup").style.display = "inline-block";
             }
-        })
+        }
 
         // Back setting.
         document.getElementById("back-setting").addEventListener("click", () => {
@@ -11,6 +11,28 @@ var initSettingsInterval = setInterval(function () {
             document.getElementById("save-settings").disabled = false;
         })
 
+        // Switch profiles.
+        document.getElementById("profile").addEventListener("change", (event) => {
+            window.__TAURI__
+                .invoke('activate_profile', { name: event.target.value })
+                .then(_ => window.__TAURI__.invoke('get_config'))
+                .then(config => {
+                    window.state.config = config;
+                    document.getElementById("save-error").innerText = "";
+                })
+                .catch(e => document.getElementById("save-error").innerText = e)
+                .finally(() => window.displaySettings());
+        })
+
+        // Reserve huge pages and tune the CPU, for XMRig running unprivileged.
+        document.getElementById("tune-system").addEventListener("click", () => {
+            let result = document.getElementById("tune-system-result");
+            window.__TAURI__
+                .invoke('tune_system')
+                .then(done => result.innerText = done.join("\n"))
+                .catch(e => result.innerText = e);
+        })
+
         // Enable saving.
         document.getElementById("monero-address").addEventListener("keyup", () => {
             document.getElementById("save-settings").disabled = false;
@@ -24,10 +46,13 @@ var initSettingsInterval = setInterval(function () {
             let moneroAddress = document.getElementById("monero-address").value;
             let blockchainFolder = document.getElementById("blockchain-dir").value;
             window.__TAURI__
-                .invoke('save_settings', { address: moneroAddress, folder: blockchainFolder });
-
-            document.getElementById("save-settings").disabled = true;
-            document.getElementById("save-effect-notice").style.display = "block";
+                .invoke('save_settings', { address: moneroAddress, folder: blockchainFolder })
+                .then(_ => {
+                    document.getElementById("save-settings").disabled = true;
+                    document.getElementById("save-error").innerText = "";
+                    document.getElementById("save-effect-notice").style.display = "block";
+                })
+                .catch(e => document.getElementById("save-error").innerText = e.message);
         })
 
         clearTimeout(initSettingsInterval);
@@ -37,8 +62,19 @@ var initSettingsInterval = setInterval(function () {
 // FUNCTIONS ----------------------------------------------------------
 
 window.displaySettings = function () {
-    document.getElementById("monero-address").value = window.state.config.pool.local.moneroAddress;
-    document.getElementById("blockchain-dir").value = window.state.config.pool.local.daemon.local.blockchainDir;
+    let select = document.getElementById("profile");
+    select.innerHTML = "";
+    for (let name of Object.keys(window.state.config.profiles)) {
+        let option = document.createElement("option");
+        option.value = name;
+        option.innerText = name;
+        select.appendChild(option);
+    }
+    select.value = window.state.config.activeProfile;
+
+    let pool = activeProfile().pool;
+    document.getElementById("monero-address").value = pool.local?.moneroAddress ?? pool.remote?.user ?? "";
+    document.getElementById("blockchain-dir").value = pool.local?.daemon.local?.blockchainDir ?? "";
 }
 
 // EVENTS -------------------------------------------------------------
@@ -0,0 +1,351 @@
+use std::{error, fmt};
+
+use serde::Serialize;
+use tauri::command;
+use tiny_keccak::{Hasher, Keccak};
+
+use crate::settings::SettingsError;
+
+const ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Monero's base58 encodes 8 byte blocks as 11 characters. A shorter final block of `n` bytes is
+/// encoded as `ENCODED_BLOCK_SIZES[n]` characters.
+const ENCODED_BLOCK_SIZES: [usize; 9] = [0, 2, 3, 5, 6, 7, 9, 10, 11];
+const FULL_BLOCK_SIZE: usize = 8;
+const FULL_ENCODED_BLOCK_SIZE: usize = 11;
+
+const CHECKSUM_SIZE: usize = 4;
+/// Network byte, public spend key and public view key.
+const STANDARD_SIZE: usize = 1 + 32 + 32;
+/// A standard address followed by an 8 byte payment ID.
+const INTEGRATED_SIZE: usize = STANDARD_SIZE + 8;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Stagenet,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AddressKind {
+    Standard,
+    Subaddress,
+    Integrated,
+}
+
+/// What a valid Monero address is.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Address {
+    pub network: Network,
+    pub kind: AddressKind,
+}
+
+impl Address {
+    /// Decode `address`, verifying its network byte, length, and checksum.
+    pub fn parse(address: &str) -> Result<Address, AddressError> {
+        let address = address.trim();
+        if address.is_empty() {
+            return Err(AddressError::Empty);
+        }
+
+        if address.len() != encoded_size(STANDARD_SIZE + CHECKSUM_SIZE)
+            && address.len() != encoded_size(INTEGRATED_SIZE + CHECKSUM_SIZE)
+        {
+            return Err(AddressError::InvalidLength {
+                length: address.len(),
+            });
+        }
+
+        let bytes = decode_base58(address)?;
+
+        let (data, checksum) = bytes.split_at(bytes.len() - CHECKSUM_SIZE);
+        if keccak256(data)[..CHECKSUM_SIZE] != *checksum {
+            return Err(AddressError::InvalidChecksum);
+        }
+
+        let (network, kind) = match data[0] {
+            18 => (Network::Mainnet, AddressKind::Standard),
+            19 => (Network::Mainnet, AddressKind::Integrated),
+            42 => (Network::Mainnet, AddressKind::Subaddress),
+            53 => (Network::Testnet, AddressKind::Standard),
+            54 => (Network::Testnet, AddressKind::Integrated),
+            63 => (Network::Testnet, AddressKind::Subaddress),
+            24 => (Network::Stagenet, AddressKind::Standard),
+            25 => (Network::Stagenet, AddressKind::Integrated),
+            36 => (Network::Stagenet, AddressKind::Subaddress),
+            byte => return Err(AddressError::UnknownNetwork { byte }),
+        };
+        let expected_size = match kind {
+            AddressKind::Integrated => INTEGRATED_SIZE,
+            _ => STANDARD_SIZE,
+        };
+        if data.len() != expected_size {
+            return Err(AddressError::InvalidLength {
+                length: address.len(),
+            });
+        }
+
+        Ok(Address { network, kind })
+    }
+}
+
+/// Check that P2Pool can pay out to `address`. P2Pool pays out in the coinbase transaction, which
+/// can only pay primary mainnet addresses.
+pub fn validate_payout_address(address: &str) -> Result<Address, AddressError> {
+    let parsed = Address::parse(address)?;
+    match parsed {
+        Address {
+            network: Network::Mainnet,
+            kind: AddressKind::Standard,
+        } => Ok(parsed),
+        Address {
+            network: Network::Mainnet,
+            kind: AddressKind::Subaddress,
+        } => Err(AddressError::Subaddress),
+        Address {
+            network: Network::Mainnet,
+            kind: AddressKind::Integrated,
+        } => Err(AddressError::Integrated),
+        Address { network, .. } => Err(AddressError::WrongNetwork { network }),
+    }
+}
+
+/// Check that `address` can be mined to.
+#[command]
+pub fn validate_address(address: String) -> Result<Address, SettingsError> {
+    validate_payout_address(&address).map_err(SettingsError::from)
+}
+
+/// Length of `size` bytes once encoded.
+fn encoded_size(size: usize) -> usize {
+    size / FULL_BLOCK_SIZE * FULL_ENCODED_BLOCK_SIZE + ENCODED_BLOCK_SIZES[size % FULL_BLOCK_SIZE]
+}
+
+fn decode_base58(encoded: &str) -> Result<Vec<u8>, AddressError> {
+    let encoded = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(encoded.len() * FULL_BLOCK_SIZE / FULL_ENCODED_BLOCK_SIZE);
+    for block in encoded.chunks(FULL_ENCODED_BLOCK_SIZE) {
+        let size = ENCODED_BLOCK_SIZES
+            .iter()
+            .position(|&size| size == block.len())
+            .ok_or(AddressError::InvalidLength {
+                length: encoded.len(),
+            })?;
+
+        let mut value: u128 = 0;
+        for &c in block {
+            let digit =
+                ALPHABET
+                    .iter()
+                    .position(|&a| a == c)
+                    .ok_or(AddressError::InvalidCharacter {
+                        character: c as char,
+                    })?;
+            value = value * 58 + digit as u128;
+        }
+        // Reject blocks that overflow the number of bytes they're supposed to decode to.
+        if (size < FULL_BLOCK_SIZE && value >> (8 * size) != 0) || value > u64::MAX as u128 {
+            return Err(AddressError::InvalidEncoding);
+        }
+
+        decoded.extend_from_slice(&(value as u64).to_be_bytes()[FULL_BLOCK_SIZE - size..]);
+    }
+    Ok(decoded)
+}
+
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    hasher.update(data);
+    let mut hash = [0; 32];
+    hasher.finalize(&mut hash);
+    hash
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "reason", rename_all = "camelCase")]
+pub enum AddressError {
+    Empty,
+    /// The address contains a character outside Monero's base58 alphabet.
+    InvalidCharacter {
+        character: char,
+    },
+    /// The address is too short or too long.
+    InvalidLength {
+        length: usize,
+    },
+    /// A block of the address decodes to more bytes than it should, so it isn't valid base58.
+    InvalidEncoding,
+    /// The address was mistyped or corrupted.
+    InvalidChecksum,
+    UnknownNetwork {
+        byte: u8,
+    },
+    /// The address belongs to a network other than mainnet.
+    WrongNetwork {
+        network: Network,
+    },
+    Subaddress,
+    Integrated,
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressError::Empty => write!(f, "No Monero address was given"),
+            AddressError::InvalidCharacter { character } => write!(
+                f,
+                "Monero addresses can't contain the character '{}'",
+                character
+            ),
+            AddressError::InvalidLength { length } => write!(
+                f,
+                "Monero addresses are 95 characters long, but this one is {}",
+                length
+            ),
+            AddressError::InvalidEncoding => write!(f, "This is not a valid Monero address"),
+            AddressError::InvalidChecksum => write!(
+                f,
+                "This address is invalid. Check that it was copied correctly"
+            ),
+            AddressError::UnknownNetwork { .. } => write!(f, "This is not a Monero address"),
+            AddressError::WrongNetwork { network } => write!(
+                f,
+                "This is a {:?} address, but Ministo mines on mainnet",
+                network
+            ),
+            AddressError::Subaddress => write!(
+                f,
+                "P2Pool can't pay out to subaddresses. Use your wallet's primary address, \
+                beginning with 4"
+            ),
+            AddressError::Integrated => write!(
+                f,
+                "P2Pool can't pay out to integrated addresses. Use your wallet's primary \
+                address, beginning with 4"
+            ),
+        }
+    }
+}
+
+impl error::Error for AddressError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Address used by the webdriver tests.
+    const MAINNET: &str = "4A1WSBQdCbUCqt3DaGfmqVFchXScF43M6c5r4B6JXT3dUwuALncU9XTEnRPmUMcB3c16kVP9Y7thFLCJ5BaMW3UmSy93w3w";
+
+    fn encode_base58(data: &[u8]) -> String {
+        let mut encoded = String::new();
+        for block in data.chunks(FULL_BLOCK_SIZE) {
+            let mut bytes = [0; FULL_BLOCK_SIZE];
+            bytes[FULL_BLOCK_SIZE - block.len()..].copy_from_slice(block);
+            let mut value = u64::from_be_bytes(bytes);
+            let mut digits = vec![b'1'; ENCODED_BLOCK_SIZES[block.len()]];
+            for digit in digits.iter_mut().rev() {
+                *digit = ALPHABET[(value % 58) as usize];
+                value /= 58;
+            }
+            encoded.push_str(std::str::from_utf8(&digits).unwrap());
+        }
+        encoded
+    }
+
+    /// An address for `network_byte` with arbitrary keys, and a payment ID if integrated.
+    fn address(network_byte: u8, integrated: bool) -> String {
+        let mut data = vec![network_byte];
+        data.extend((0..64).map(|i| i as u8));
+        if integrated {
+            data.extend([7; 8]);
+        }
+        let checksum = keccak256(&data);
+        data.extend_from_slice(&checksum[..CHECKSUM_SIZE]);
+        encode_base58(&data)
+    }
+
+    #[test]
+    fn base58_round_trip() {
+        let data: Vec<u8> = (0..69).map(|i| (i * 37) as u8).collect();
+        assert_eq!(decode_base58(&encode_base58(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn mainnet_primary_address() {
+        assert_eq!(
+            validate_payout_address(MAINNET),
+            Ok(Address {
+                network: Network::Mainnet,
+                kind: AddressKind::Standard
+            })
+        );
+    }
+
+    #[test]
+    fn networks_and_kinds() {
+        let cases = [
+            (18, false, Network::Mainnet, AddressKind::Standard),
+            (19, true, Network::Mainnet, AddressKind::Integrated),
+            (42, false, Network::Mainnet, AddressKind::Subaddress),
+            (53, false, Network::Testnet, AddressKind::Standard),
+            (54, true, Network::Testnet, AddressKind::Integrated),
+            (63, false, Network::Testnet, AddressKind::Subaddress),
+            (24, false, Network::Stagenet, AddressKind::Standard),
+            (25, true, Network::Stagenet, AddressKind::Integrated),
+            (36, false, Network::Stagenet, AddressKind::Subaddress),
+        ];
+        for (byte, integrated, network, kind) in cases {
+            let address = address(byte, integrated);
+            assert_eq!(address.len(), if integrated { 106 } else { 95 });
+            assert_eq!(Address::parse(&address), Ok(Address { network, kind }));
+        }
+    }
+
+    #[test]
+    fn rejects_unpayable_addresses() {
+        assert_eq!(
+            validate_payout_address(&address(42, false)),
+            Err(AddressError::Subaddress)
+        );
+        assert_eq!(
+            validate_payout_address(&address(19, true)),
+            Err(AddressError::Integrated)
+        );
+        assert_eq!(
+            validate_payout_address(&address(24, false)),
+            Err(AddressError::WrongNetwork {
+                network: Network::Stagenet
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_addresses() {
+        assert_eq!(validate_payout_address("  "), Err(AddressError::Empty));
+        assert_eq!(
+            validate_payout_address(&MAINNET[..94]),
+            Err(AddressError::InvalidLength { length: 94 })
+        );
+        assert_eq!(
+            validate_payout_address(&MAINNET.replace('W', "0")),
+            Err(AddressError::InvalidCharacter { character: '0' })
+        );
+        assert_eq!(
+            validate_payout_address(&MAINNET.replace("4A1", "4A2")),
+            Err(AddressError::InvalidChecksum)
+        );
+        // The final block of 5 bytes can't hold this much.
+        assert_eq!(
+            validate_payout_address(&format!("{}zzzzzzz", &MAINNET[..88])),
+            Err(AddressError::InvalidEncoding)
+        );
+        assert_eq!(
+            validate_payout_address(&address(99, false)),
+            Err(AddressError::UnknownNetwork { byte: 99 })
+        );
+    }
+}
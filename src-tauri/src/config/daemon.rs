@@ -1,8 +1,15 @@
-use std::{default::Default, path::PathBuf};
+use std::{
+    default::Default,
+    fmt,
+    path::{Path, PathBuf},
+};
 
+use anyhow::{Error, Result};
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+use super::port::{self, Port};
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
 pub enum Daemon {
@@ -10,13 +17,42 @@ pub enum Daemon {
     Remote(RemoteDaemon),
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
 pub struct LocalDaemon {
     pub blockchain_dir: String,
     /// Verbosity of Monerod. Should be an integer between 0 and 4.
     pub monerod_verbosity: u8,
+    /// Prune the blockchain, keeping roughly a third of it.
+    pub prune: bool,
+    /// Download blocks that are already pruned, to save bandwidth. Only applies when pruning.
+    pub sync_pruned_blocks: bool,
+    pub db_sync_mode: DbSyncMode,
+    /// Connect to peers over IPv6, as well as IPv4.
+    pub ipv6: bool,
+    /// Follow the MoneroPulse checkpoints published over DNS.
+    pub dns_checkpoints: bool,
+    pub rpc_port: Port,
+    pub p2p_port: Port,
+    pub zmq_port: Port,
+    /// Maximum number of outgoing peers. If left blank, monerod's default is used.
+    pub out_peers: Option<u32>,
+    /// Maximum number of incoming peers. If left blank, monerod's default is used.
+    pub in_peers: Option<u32>,
+    /// Upload limit, in kB/s. If left blank, monerod's default is used.
+    pub limit_rate_up: Option<u32>,
+    /// Download limit, in kB/s. If left blank, monerod's default is used.
+    pub limit_rate_down: Option<u32>,
+    /// File listing IPs to ban, one per line.
+    pub ban_list: Option<String>,
+    /// Peers to stay connected to, as `host:port`.
+    pub priority_nodes: Vec<String>,
+    /// The only peers to connect to, as `host:port`.
+    pub exclusive_nodes: Vec<String>,
+    /// Further arguments passed to monerod as they are.
+    pub extra_args: Vec<String>,
 }
 
 impl Default for LocalDaemon {
@@ -24,16 +60,184 @@ impl Default for LocalDaemon {
         LocalDaemon {
             blockchain_dir: default_blockchain_dir().to_string_lossy().into_owned(),
             monerod_verbosity: 0, // Low verbosity
+            prune: true,
+            sync_pruned_blocks: true,
+            db_sync_mode: DbSyncMode::default(),
+            ipv6: true,
+            dns_checkpoints: false,
+            rpc_port: Port::Fixed(port::MONEROD_RPC),
+            p2p_port: Port::Fixed(port::MONEROD_P2P),
+            zmq_port: Port::Fixed(port::MONEROD_ZMQ),
+            out_peers: None,
+            in_peers: None,
+            limit_rate_up: None,
+            limit_rate_down: None,
+            ban_list: None,
+            priority_nodes: Vec::new(),
+            exclusive_nodes: Vec::new(),
+            extra_args: Vec::new(),
+        }
+    }
+}
+
+impl LocalDaemon {
+    /// Check the settings before monerod is started with them, so that mistakes are reported
+    /// plainly rather than buried in monerod's output.
+    pub fn validate(&self) -> Result<()> {
+        if self.blockchain_dir.trim().is_empty() {
+            return Err(Error::msg("Blockchain storage location not configured"));
+        }
+        if self.monerod_verbosity > 4 {
+            return Err(Error::msg("Monerod verbosity must be between 0 and 4"));
+        }
+
+        let ports = [
+            ("RPC", self.rpc_port),
+            ("P2P", self.p2p_port),
+            ("ZMQ", self.zmq_port),
+        ];
+        for (i, (name, port)) in ports.iter().enumerate() {
+            if *port == Port::Auto {
+                continue;
+            }
+            if let Some((other, _)) = ports[..i].iter().find(|(_, other)| other == port) {
+                return Err(Error::msg(format!(
+                    "Monerod's {} and {} ports are both {}",
+                    other, name, port
+                )));
+            }
+        }
+
+        if self.db_sync_mode.interval.count() == 0 {
+            return Err(Error::msg("Database sync interval can't be 0"));
+        }
+        if self.limit_rate_up == Some(0) || self.limit_rate_down == Some(0) {
+            return Err(Error::msg(
+                "Bandwidth limits can't be 0. Leave them blank for no limit",
+            ));
+        }
+
+        if let Some(ban_list) = &self.ban_list {
+            if !Path::new(ban_list).is_file() {
+                return Err(Error::msg(format!("Ban list {} does not exist", ban_list)));
+            }
+        }
+        for node in self.priority_nodes.iter().chain(&self.exclusive_nodes) {
+            validate_node(node)?;
+        }
+
+        for arg in &self.extra_args {
+            let flag = arg.split('=').next().unwrap_or(arg);
+            if MANAGED_FLAGS.contains(&flag) {
+                return Err(Error::msg(format!(
+                    "{} is set by Ministo, and can't be passed as an extra argument",
+                    flag
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Flags Ministo relies on being set the way it sets them.
+const MANAGED_FLAGS: [&str; 7] = [
+    "--data-dir",
+    "--rpc-bind-port",
+    "--p2p-bind-port",
+    "--zmq-pub",
+    "--non-interactive",
+    "--detach",
+    "--log-level",
+];
+
+/// Check that `node` is given as `host:port`.
+fn validate_node(node: &str) -> Result<()> {
+    let invalid = || Error::msg(format!("Node {} must be given as host:port", node));
+    let (host, port) = node.rsplit_once(':').ok_or_else(invalid)?;
+    if host.is_empty() || port.parse::<u16>().map_or(true, |port| port == 0) {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// How monerod writes the blockchain database to disk, passed as `--db-sync-mode`.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct DbSyncMode {
+    pub safety: DbSafety,
+    /// Write to disk in the background, rather than waiting for each write to finish.
+    pub asynchronous: bool,
+    /// How much is written between syncs.
+    pub interval: SyncInterval,
+}
+
+impl Default for DbSyncMode {
+    fn default() -> Self {
+        // Sync resiliently in case of system crash.
+        DbSyncMode {
+            safety: DbSafety::Safe,
+            asynchronous: false,
+            interval: SyncInterval::Bytes(250_000_000),
         }
     }
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+impl fmt::Display for DbSyncMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let safety = match self.safety {
+            DbSafety::Safe => "safe",
+            DbSafety::Fast => "fast",
+            DbSafety::Fastest => "fastest",
+        };
+        let sync = if self.asynchronous { "async" } else { "sync" };
+        match self.interval {
+            SyncInterval::Blocks(blocks) => write!(f, "{}:{}:{}blocks", safety, sync, blocks),
+            SyncInterval::Bytes(bytes) => write!(f, "{}:{}:{}bytes", safety, sync, bytes),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub enum DbSafety {
+    Safe,
+    Fast,
+    Fastest,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub enum SyncInterval {
+    Blocks(u64),
+    Bytes(u64),
+}
+
+impl SyncInterval {
+    fn count(&self) -> u64 {
+        match self {
+            SyncInterval::Blocks(count) | SyncInterval::Bytes(count) => *count,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
 pub struct RemoteDaemon {
-    ip: String,
-    port: u16,
+    pub ip: String,
+    pub rpc_port: u16,
+    #[serde(default = "default_zmq_port")]
+    pub zmq_port: u16,
+    /// Credentials for the daemon's RPC, formatted as `username:password`.
+    #[serde(default)]
+    pub rpc_login: Option<String>,
+}
+
+fn default_zmq_port() -> u16 {
+    port::MONEROD_ZMQ
 }
 
 /// Default blockchain location according to
@@ -47,3 +251,82 @@ pub fn default_blockchain_dir() -> PathBuf {
             .join(".bitmonero/")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_db_sync_mode() {
+        assert_eq!(
+            DbSyncMode::default().to_string(),
+            "safe:sync:250000000bytes"
+        );
+        let fast = DbSyncMode {
+            safety: DbSafety::Fast,
+            asynchronous: true,
+            interval: SyncInterval::Blocks(1000),
+        };
+        assert_eq!(fast.to_string(), "fast:async:1000blocks");
+    }
+
+    #[test]
+    fn parses_older_local_daemons() {
+        let daemon: LocalDaemon =
+            serde_json::from_str(r#"{"blockchainDir": "/blockchain", "monerodVerbosity": 1}"#)
+                .unwrap();
+        assert_eq!(
+            daemon,
+            LocalDaemon {
+                blockchain_dir: "/blockchain".to_string(),
+                monerod_verbosity: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn validates() {
+        assert!(LocalDaemon::default().validate().is_ok());
+
+        let invalid = [
+            LocalDaemon {
+                p2p_port: Port::Fixed(18081),
+                ..Default::default()
+            },
+            LocalDaemon {
+                limit_rate_up: Some(0),
+                ..Default::default()
+            },
+            LocalDaemon {
+                ban_list: Some("/does/not/exist".to_string()),
+                ..Default::default()
+            },
+            LocalDaemon {
+                priority_nodes: vec!["node.example.com".to_string()],
+                ..Default::default()
+            },
+            LocalDaemon {
+                exclusive_nodes: vec![":18080".to_string()],
+                ..Default::default()
+            },
+            LocalDaemon {
+                extra_args: vec!["--data-dir=/elsewhere".to_string()],
+                ..Default::default()
+            },
+        ];
+        for daemon in invalid {
+            assert!(daemon.validate().is_err(), "{:?}", daemon);
+        }
+
+        let valid = LocalDaemon {
+            rpc_port: Port::Auto,
+            zmq_port: Port::Auto,
+            priority_nodes: vec!["node.example.com:18080".to_string()],
+            exclusive_nodes: vec!["[::1]:18080".to_string()],
+            extra_args: vec!["--max-concurrency".to_string(), "2".to_string()],
+            ..Default::default()
+        };
+        assert!(valid.validate().is_ok());
+    }
+}
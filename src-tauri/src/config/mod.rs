@@ -1,74 +1,410 @@
 pub mod daemon;
 pub mod pool;
+pub mod port;
+pub mod schedule;
+pub mod xmrig;
 
 use std::string::ToString;
 use std::{
+    collections::BTreeMap,
     default::Default,
+    error, fmt,
+    fs::{self, File},
+    io::{self, Write},
     path::{Path, PathBuf},
 };
-use std::{fs, fs::File, io::Read};
 
-use anyhow::Result;
+use anyhow::{Error, Result};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
-use self::pool::{LocalPool, Pool};
+use self::{
+    pool::{LocalPool, Pool, RemotePool},
+    schedule::ScheduleConfig,
+    xmrig::XmrigConfig,
+};
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(default)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
 pub struct Config {
-    pub pool: Pool,
-    pub xmrig: XmrigConfig,
+    /// Layout version of the configuration file, used to migrate files written by older versions
+    /// of Ministo.
+    pub version: u32,
+    /// Name of the profile that is mined with.
+    pub active_profile: String,
+    pub profiles: BTreeMap<String, Profile>,
+    pub supervisor: SupervisorConfig,
 }
 
 impl Config {
     pub fn new(monero_address: &str) -> Config {
-        Config {
+        let profile = Profile {
             pool: Pool::Local(LocalPool {
                 monero_address: Some(monero_address.to_string()),
                 ..Default::default()
             }),
             ..Default::default()
+        };
+        Config {
+            profiles: BTreeMap::from([(DEFAULT_PROFILE.to_string(), profile)]),
+            ..Default::default()
         }
     }
 
-    /// Open configuration file, returning an error if it doesn't exist. Panics if the file cannot be
-    /// parsed.
-    pub fn open(path: &Path) -> Result<Config> {
-        let mut full_path = path.to_path_buf();
-        if !path.ends_with("ministo.json") {
-            full_path.push("ministo.json");
+    /// The active profile.
+    pub fn profile(&self) -> &Profile {
+        self.profiles
+            .get(&self.active_profile)
+            .expect("active profile is missing")
+    }
+
+    pub fn profile_mut(&mut self) -> &mut Profile {
+        self.profiles
+            .get_mut(&self.active_profile)
+            .expect("active profile is missing")
+    }
+
+    /// Add `profile` as `name`, which must not be taken yet.
+    pub fn add_profile(&mut self, name: &str, profile: Profile) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > MAX_PROFILE_NAME_LENGTH {
+            return Err(Error::msg(format!(
+                "Profile names must be between 1 and {} characters long",
+                MAX_PROFILE_NAME_LENGTH
+            )));
         }
+        if self.profiles.contains_key(name) {
+            return Err(Error::msg(format!(
+                "A profile named '{}' already exists",
+                name
+            )));
+        }
+        self.profiles.insert(name.to_string(), profile);
+        Ok(())
+    }
 
-        let mut file_contents = String::new();
-        File::open(&full_path)?.read_to_string(&mut file_contents)?;
+    /// Remove the profile `name`. The active profile can't be removed.
+    pub fn remove_profile(&mut self, name: &str) -> Result<Profile> {
+        if name == self.active_profile {
+            return Err(Error::msg(format!(
+                "'{}' is the active profile. Activate another profile before deleting it",
+                name
+            )));
+        }
+        self.profiles
+            .remove(name)
+            .ok_or_else(|| Error::msg(format!("There is no profile named '{}'", name)))
+    }
+
+    /// Make `name` the active profile.
+    pub fn activate_profile(&mut self, name: &str) -> Result<()> {
+        if !self.profiles.contains_key(name) {
+            return Err(Error::msg(format!("There is no profile named '{}'", name)));
+        }
+        self.active_profile = name.to_string();
+        Ok(())
+    }
 
-        let config: Config =
-            serde_json::from_str(&file_contents).expect("failed to parse ministo.json");
+    /// Open configuration file, given either the file itself or the directory containing it.
+    pub fn open(path: &Path) -> Result<Config, ConfigError> {
+        Config::read(&config_file(path))
+    }
+
+    fn read(file: &Path) -> Result<Config, ConfigError> {
+        let contents = fs::read_to_string(file).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => ConfigError::NotFound(file.to_path_buf()),
+            _ => ConfigError::Io {
+                path: file.to_path_buf(),
+                source: e,
+            },
+        })?;
+        Config::parse(file, &contents)
+    }
+
+    /// Parse a configuration file, first migrating it to the current layout if it was written by
+    /// an older version of Ministo.
+    fn parse(file: &Path, contents: &str) -> Result<Config, ConfigError> {
+        let parse_error = |e| ConfigError::Parse {
+            path: file.to_path_buf(),
+            source: e,
+        };
+
+        let mut value: Value = serde_json::from_str(contents).map_err(parse_error)?;
+        // Files written before versioning was introduced have no version.
+        let version = match value.get("version") {
+            None => 0,
+            Some(version) => version.as_u64().unwrap_or(CONFIG_VERSION as u64),
+        };
+        if version > CONFIG_VERSION as u64 {
+            return Err(ConfigError::NewerVersion {
+                path: file.to_path_buf(),
+                version,
+            });
+        }
+        let config: Config = if version == CONFIG_VERSION as u64 {
+            // Parse the original text, so that errors point at the right line.
+            serde_json::from_str(contents).map_err(parse_error)?
+        } else {
+            if let Some(config) = value.as_object_mut() {
+                for migration in &MIGRATIONS[version as usize..] {
+                    migration(config);
+                }
+                config.insert("version".to_string(), CONFIG_VERSION.into());
+            }
+            serde_json::from_value(value).map_err(parse_error)?
+        };
+
+        if !config.profiles.contains_key(&config.active_profile) {
+            return Err(ConfigError::Invalid {
+                path: file.to_path_buf(),
+                message: format!(
+                    "the active profile '{}' does not exist",
+                    config.active_profile
+                ),
+            });
+        }
         Ok(config)
     }
 
-    /// Create a new configuration file.
+    /// Save configuration file. The file is replaced atomically, and the configuration it held is
+    /// kept as a backup.
     pub fn save(&self, path: &Path) -> Result<()> {
-        let mut full_path = path.to_path_buf();
-        if !path.ends_with("ministo.json") {
-            full_path.push("ministo.json");
-        }
+        let file = config_file(path);
+        let contents = serde_json::to_string_pretty(&self)?;
+
+        // Write everything out before touching the original, so that a crash can't leave a
+        // partially written configuration behind.
+        let temp_file = with_suffix(&file, ".tmp");
+        let mut temp = File::create(&temp_file)?;
+        temp.write_all(contents.as_bytes())?;
+        temp.sync_all()?;
+        drop(temp);
 
-        fs::write(full_path, serde_json::to_string_pretty(&self)?)?;
+        back_up(&file, &contents)?;
+        fs::rename(&temp_file, &file)?;
         Ok(())
     }
+
+    /// Replace the configuration file with its newest backup that can still be parsed.
+    pub fn restore_backup(path: &Path) -> Result<Config> {
+        let file = config_file(path);
+        for n in 1..=BACKUP_COUNT {
+            if let Ok(config) = Config::read(&backup_file(&file, n)) {
+                config.save(path)?;
+                return Ok(config);
+            }
+        }
+        Err(Error::msg(format!(
+            "No usable backup of {} was found",
+            file.display()
+        )))
+    }
+}
+
+/// Version of the configuration layout written by this version of Ministo. Bumped whenever a
+/// setting is added or accepts new values, even if older files need no migrating, so that older
+/// versions of Ministo refuse newer files rather than failing to parse them.
+pub const CONFIG_VERSION: u32 = 9;
+
+/// Migrations between layouts, in order: `MIGRATIONS[n]` upgrades version `n` to version `n + 1`.
+const MIGRATIONS: [fn(&mut Map<String, Value>); CONFIG_VERSION as usize] = [
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    // Version 3 added monerod's options to a local daemon.
+    only_additions,
+    // Version 4 added configurable ports, which can also be `"auto"`.
+    only_additions,
+    // Version 5 added XMRig's `privileges`.
+    only_additions,
+    // Version 6 added XMRig's CPU, RandomX and donation settings.
+    only_additions,
+    // Version 7 added a profile's `failoverPools`.
+    only_additions,
+    // Version 8 added idle mining.
+    only_additions,
+    // Version 9 added the mining schedule.
+    only_additions,
+];
+
+/// Version 1 renamed a remote daemon's `port` to `rpcPort`, as it also gained a `zmqPort`.
+fn migrate_v0_to_v1(config: &mut Map<String, Value>) {
+    let remote_daemon = config
+        .get_mut("pool")
+        .and_then(|pool| pool.get_mut("local"))
+        .and_then(|pool| pool.get_mut("daemon"))
+        .and_then(|daemon| daemon.get_mut("remote"))
+        .and_then(Value::as_object_mut);
+    if let Some(daemon) = remote_daemon {
+        if let Some(port) = daemon.remove("port") {
+            daemon.entry("rpcPort").or_insert(port);
+        }
+    }
+}
+
+/// Version 2 moved `pool` and `xmrig` into named profiles, keeping them as the default profile.
+fn migrate_v1_to_v2(config: &mut Map<String, Value>) {
+    let mut profile = Map::new();
+    for key in ["pool", "xmrig"] {
+        if let Some(value) = config.remove(key) {
+            profile.insert(key.to_string(), value);
+        }
+    }
+    let mut profiles = Map::new();
+    profiles.insert(DEFAULT_PROFILE.to_string(), Value::Object(profile));
+    config.insert("profiles".to_string(), Value::Object(profiles));
+    config.insert("activeProfile".to_string(), DEFAULT_PROFILE.into());
+}
+
+/// For versions that only added settings, whose defaults keep older files working as they did.
+fn only_additions(_config: &mut Map<String, Value>) {}
+
+/// Number of previous configurations kept, from `ministo.json.bak.1` (the newest) to
+/// `ministo.json.bak.5`.
+const BACKUP_COUNT: usize = 5;
+
+/// Path of the configuration file, given either the file itself or the directory containing it.
+fn config_file(path: &Path) -> PathBuf {
+    if path.ends_with("ministo.json") {
+        path.to_path_buf()
+    } else {
+        path.join("ministo.json")
+    }
+}
+
+fn backup_file(file: &Path, n: usize) -> PathBuf {
+    with_suffix(file, &format!(".bak.{}", n))
+}
+
+fn with_suffix(file: &Path, suffix: &str) -> PathBuf {
+    let mut path = file.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Add the current configuration file to the backups before it's replaced with `new_contents`.
+/// Files that can't be parsed are set aside as `ministo.json.invalid` instead, so that they never
+/// push out a good backup.
+fn back_up(file: &Path, new_contents: &str) -> Result<()> {
+    let contents = match fs::read_to_string(file) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    if Config::parse(file, &contents).is_err() {
+        fs::copy(file, with_suffix(file, ".invalid"))?;
+        return Ok(());
+    }
+    // Saving without changes shouldn't push out older backups.
+    if contents == new_contents {
+        return Ok(());
+    }
+
+    for n in (1..BACKUP_COUNT).rev() {
+        let backup = backup_file(file, n);
+        if backup.exists() {
+            fs::rename(&backup, backup_file(file, n + 1))?;
+        }
+    }
+    fs::copy(file, backup_file(file, 1))?;
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// There is no configuration file yet.
+    NotFound(PathBuf),
+    /// The configuration file could not be read.
+    Io { path: PathBuf, source: io::Error },
+    /// The configuration file is not valid JSON, or doesn't match what Ministo expects.
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// The configuration file was written by a newer version of Ministo.
+    NewerVersion { path: PathBuf, version: u64 },
+    /// The configuration file parsed, but doesn't make sense.
+    Invalid { path: PathBuf, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::NotFound(path) => write!(f, "{} does not exist", path.display()),
+            ConfigError::Io { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+            // serde_json's message names the offending field, line and column.
+            ConfigError::Parse { path, source } => {
+                write!(f, "failed to parse {}: {}", path.display(), source)
+            }
+            ConfigError::NewerVersion { path, version } => write!(
+                f,
+                "{} was written by a newer version of Ministo (configuration version {}, but \
+                only up to {} is supported); update Ministo to use it",
+                path.display(),
+                version,
+                CONFIG_VERSION
+            ),
+            ConfigError::Invalid { path, message } => {
+                write!(f, "{} is invalid: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+impl error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ConfigError::NotFound(_)
+            | ConfigError::NewerVersion { .. }
+            | ConfigError::Invalid { .. } => None,
+            ConfigError::Io { source, .. } => Some(source),
+            ConfigError::Parse { source, .. } => Some(source),
+        }
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
+            version: CONFIG_VERSION,
+            active_profile: DEFAULT_PROFILE.to_string(),
+            profiles: BTreeMap::from([(DEFAULT_PROFILE.to_string(), Profile::default())]),
+            supervisor: SupervisorConfig::default(),
+        }
+    }
+}
+
+/// Name of the profile created along with the configuration file.
+pub const DEFAULT_PROFILE: &str = "default";
+
+const MAX_PROFILE_NAME_LENGTH: usize = 64;
+
+/// A named set of mining settings, which can be switched between.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub pool: Pool,
+    /// Pools XMRig falls back to, in order, while the ones before them can't be reached. XMRig
+    /// returns to `pool` once it can.
+    pub failover_pools: Vec<RemotePool>,
+    pub xmrig: XmrigConfig,
+    pub idle: IdleConfig,
+    pub schedule: ScheduleConfig,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile {
             pool: Pool::Local(LocalPool::default()),
-            xmrig: XmrigConfig {
-                verbose: false,
-                bearer_token: None,
-            },
+            failover_pools: Vec::new(),
+            xmrig: XmrigConfig::default(),
+            idle: IdleConfig::default(),
+            schedule: ScheduleConfig::default(),
         }
     }
 }
@@ -86,11 +422,290 @@ pub fn default_configuraton_dir() -> PathBuf {
     }
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+/// Directory for the files Ministo manages itself, next to the configuration file at `path`.
+pub fn data_dir(path: &Path) -> PathBuf {
+    let file = config_file(path);
+    file.parent().unwrap_or(&file).join("ministo")
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(default)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
-pub struct XmrigConfig {
-    pub verbose: bool,
-    /// Bearer token for API access. If left blank, a secure token will be generated randomly.
-    pub bearer_token: Option<String>,
+pub struct SupervisorConfig {
+    /// Number of consecutive crashes tolerated before a process is no longer restarted.
+    pub max_restarts: u32,
+    /// Delay before the first restart. Doubles with each consecutive crash.
+    pub initial_backoff_secs: u64,
+    /// Upper limit for the delay between restarts.
+    pub max_backoff_secs: u64,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        SupervisorConfig {
+            max_restarts: 5,
+            initial_backoff_secs: 5,
+            max_backoff_secs: 300,
+        }
+    }
+}
+
+/// Mining only while the user is away from the computer.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct IdleConfig {
+    /// Hold back mining while the user is active.
+    pub enabled: bool,
+    /// How long the user has to be idle before mining at full speed again.
+    pub idle_secs: u64,
+    /// Threads XMRig keeps mining with while the user is active. If left blank or 0, mining is
+    /// paused instead.
+    pub active_threads: Option<u32>,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        IdleConfig {
+            enabled: false,
+            idle_secs: 300,
+            active_threads: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::tempdir;
+
+    use super::{daemon::Daemon, xmrig::XmrigPrivileges};
+
+    fn address(config: &Config) -> Option<String> {
+        match &config.profile().pool {
+            Pool::Local(pool) => pool.monero_address.clone(),
+            Pool::Remote(_) => None,
+        }
+    }
+
+    #[test]
+    fn saves_and_opens() {
+        let dir = tempdir().unwrap();
+        Config::new("first").save(dir.path()).unwrap();
+
+        let opened = Config::open(dir.path()).unwrap();
+        assert_eq!(address(&opened).as_deref(), Some("first"));
+        assert!(!dir.path().join("ministo.json.tmp").exists());
+    }
+
+    #[test]
+    fn rotates_backups() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("ministo.json");
+        for n in 0..=BACKUP_COUNT + 1 {
+            Config::new(&n.to_string()).save(&file).unwrap();
+            Config::new(&n.to_string()).save(&file).unwrap();
+        }
+
+        let newest = Config::read(&backup_file(&file, 1)).unwrap();
+        assert_eq!(address(&newest), Some(BACKUP_COUNT.to_string()));
+        let oldest = Config::read(&backup_file(&file, BACKUP_COUNT)).unwrap();
+        assert_eq!(address(&oldest), Some("1".to_string()));
+        assert!(!backup_file(&file, BACKUP_COUNT + 1).exists());
+    }
+
+    #[test]
+    fn describes_parse_errors() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("ministo.json");
+        fs::write(
+            &file,
+            format!(
+                "{{\n  \"version\": {},\n  \"colour\": \"orange\"\n}}",
+                CONFIG_VERSION
+            ),
+        )
+        .unwrap();
+
+        match Config::open(&file) {
+            Err(e @ ConfigError::Parse { .. }) => {
+                let message = e.to_string();
+                assert!(message.contains("unknown field `colour`"), "{}", message);
+                assert!(message.contains("line 3"), "{}", message);
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        assert!(matches!(
+            Config::open(&dir.path().join("missing")),
+            Err(ConfigError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn migrates_v0() {
+        // As written before versioning, with a remote daemon's RPC port named `port`.
+        let v0 = r#"{
+            "pool": {
+                "local": {
+                    "moneroAddress": "4abc",
+                    "chain": "mini",
+                    "p2poolVerbosity": 2,
+                    "daemon": {"remote": {"ip": "node.example.com", "port": 18089}}
+                }
+            },
+            "xmrig": {"verbose": false, "bearerToken": null}
+        }"#;
+        let mut value: Value = serde_json::from_str(v0).unwrap();
+        migrate_v0_to_v1(value.as_object_mut().unwrap());
+        assert_eq!(
+            value["pool"]["local"]["daemon"]["remote"],
+            serde_json::json!({"ip": "node.example.com", "rpcPort": 18089})
+        );
+
+        let config = Config::parse(Path::new("ministo.json"), v0).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        match &config.profile().pool {
+            Pool::Local(LocalPool {
+                daemon: Daemon::Remote(daemon),
+                ..
+            }) => assert_eq!(daemon.rpc_port, 18089),
+            other => panic!("expected a remote daemon, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn migrates_v0_local_daemon() {
+        let v0 = r#"{"pool": {"local": {"daemon": {"local": {
+            "blockchainDir": "/blockchain", "monerodVerbosity": 0
+        }}}}}"#;
+        let config = Config::parse(Path::new("ministo.json"), v0).unwrap();
+        assert!(matches!(
+            config.profile().pool,
+            Pool::Local(LocalPool {
+                daemon: Daemon::Local(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn migrates_v1() {
+        let v1 = r#"{
+            "version": 1,
+            "pool": {"remote": {"ip": "pool.example.com", "port": 443, "user": "me"}},
+            "xmrig": {"verbose": true, "bearerToken": null},
+            "supervisor": {"maxRestarts": 2, "initialBackoffSecs": 5, "maxBackoffSecs": 300}
+        }"#;
+        let config = Config::parse(Path::new("ministo.json"), v1).unwrap();
+        assert_eq!(config.active_profile, DEFAULT_PROFILE);
+        assert_eq!(config.profiles.len(), 1);
+        assert!(config.profile().xmrig.verbose);
+        // XMRig keeps running as root, as it did before privileges could be chosen.
+        assert_eq!(config.profile().xmrig.privileges, XmrigPrivileges::Root);
+        assert!(matches!(&config.profile().pool, Pool::Remote(pool) if pool.port == 443));
+        assert_eq!(config.supervisor.max_restarts, 2);
+    }
+
+    #[test]
+    fn migrates_v2() {
+        // As written by the first version with profiles, before any settings were added to them.
+        let v2 = r#"{
+            "version": 2,
+            "activeProfile": "default",
+            "profiles": {
+                "default": {
+                    "pool": {"local": {
+                        "moneroAddress": "4abc",
+                        "chain": "mini",
+                        "p2poolVerbosity": 2,
+                        "daemon": {"local": {"blockchainDir": "/blockchain", "monerodVerbosity": 0}}
+                    }},
+                    "xmrig": {"verbose": false, "bearerToken": null}
+                }
+            },
+            "supervisor": {"maxRestarts": 5, "initialBackoffSecs": 5, "maxBackoffSecs": 300}
+        }"#;
+        let config = Config::parse(Path::new("ministo.json"), v2).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.profile().xmrig, XmrigConfig::default());
+        match &config.profile().pool {
+            Pool::Local(pool) => assert_eq!(pool.monero_address.as_deref(), Some("4abc")),
+            other => panic!("expected a local pool, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn manages_profiles() {
+        let mut config = Config::new("4abc");
+        let remote = Profile {
+            pool: Pool::Remote(pool::RemotePool {
+                ip: "pool.example.com".to_string(),
+                port: 443,
+                user: None,
+                password: None,
+                tls: true,
+                rig_id: None,
+                algorithm: None,
+                coin: None,
+            }),
+            ..Profile::default()
+        };
+        config.add_profile(" testing ", remote.clone()).unwrap();
+        assert!(config.add_profile("testing", Profile::default()).is_err());
+        assert!(config.add_profile("  ", Profile::default()).is_err());
+
+        config.activate_profile("testing").unwrap();
+        assert_eq!(config.profile(), &remote);
+        assert!(config.activate_profile("missing").is_err());
+        assert!(config.remove_profile("testing").is_err());
+        config.remove_profile(DEFAULT_PROFILE).unwrap();
+        assert_eq!(config.profiles.len(), 1);
+    }
+
+    #[test]
+    fn rejects_missing_active_profile() {
+        let contents = format!(
+            r#"{{"version": {}, "activeProfile": "missing"}}"#,
+            CONFIG_VERSION
+        );
+        assert!(matches!(
+            Config::parse(Path::new("ministo.json"), &contents),
+            Err(ConfigError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn refuses_newer_versions() {
+        let newer = format!(r#"{{"version": {}}}"#, CONFIG_VERSION + 1);
+        match Config::parse(Path::new("ministo.json"), &newer) {
+            Err(e @ ConfigError::NewerVersion { .. }) => {
+                assert!(e.to_string().contains("newer version of Ministo"))
+            }
+            other => panic!("expected a newer version error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn restores_last_good_backup() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("ministo.json");
+        Config::new("good").save(&file).unwrap();
+        Config::new("newer").save(&file).unwrap();
+        fs::write(&file, "{\"pool\": ").unwrap();
+
+        let restored = Config::restore_backup(&file).unwrap();
+        assert_eq!(address(&restored).as_deref(), Some("good"));
+        assert_eq!(
+            address(&Config::open(&file).unwrap()).as_deref(),
+            Some("good")
+        );
+        // The broken file is set aside rather than lost.
+        assert_eq!(
+            fs::read_to_string(with_suffix(&file, ".invalid")).unwrap(),
+            "{\"pool\": "
+        );
+    }
 }
@@ -1,8 +1,11 @@
 use serde::{Deserialize, Serialize};
 
-use super::daemon::{Daemon, LocalDaemon};
+use super::{
+    daemon::{Daemon, LocalDaemon},
+    port::{self, Port},
+};
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
 pub enum Pool {
@@ -10,7 +13,7 @@ pub enum Pool {
     Remote(RemotePool),
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 #[serde(default)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
@@ -20,6 +23,9 @@ pub struct LocalPool {
     /// Verbosity of P2Pool. Should be an integer between 0 and 6.
     pub p2pool_verbosity: u8,
     pub daemon: Daemon,
+    /// Port P2Pool accepts miners on. Fixed by default, as other miners on the network may be
+    /// pointed at it.
+    pub stratum_port: Port,
 }
 
 impl Default for LocalPool {
@@ -29,19 +35,38 @@ impl Default for LocalPool {
             daemon: Daemon::Local(LocalDaemon::default()),
             chain: P2poolChain::Main,
             p2pool_verbosity: 2, // Moderate verbosity
+            stratum_port: Port::Fixed(port::P2POOL_STRATUM),
         }
     }
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
 pub struct RemotePool {
     pub ip: String,
     pub port: u16,
+    /// Wallet address or username to mine as.
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub tls: bool,
+    #[serde(default)]
+    pub rig_id: Option<String>,
+    /// Mining algorithm, such as `rx/0`. If left blank, XMRig picks one based on `coin`.
+    #[serde(default)]
+    pub algorithm: Option<String>,
+    #[serde(default = "default_coin")]
+    pub coin: Option<String>,
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+fn default_coin() -> Option<String> {
+    Some("monero".to_string())
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
 pub enum P2poolChain {
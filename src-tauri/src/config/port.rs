@@ -0,0 +1,100 @@
+use std::fmt;
+
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// Port monerod serves RPC on when nothing else is configured.
+pub const MONEROD_RPC: u16 = 18081;
+/// Port monerod accepts peers on when nothing else is configured.
+pub const MONEROD_P2P: u16 = 18080;
+/// Port monerod publishes ZMQ notifications on when nothing else is configured.
+pub const MONEROD_ZMQ: u16 = 18083;
+/// Port P2Pool accepts miners on when nothing else is configured.
+pub const P2POOL_STRATUM: u16 = 3333;
+/// Port XMRig serves its HTTP API on when nothing else is configured.
+pub const XMRIG_API: u16 = 3334;
+
+/// A port for one of our processes to listen on. Written as a number, or as `"auto"` to use any
+/// free port.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Port {
+    Auto,
+    Fixed(u16),
+}
+
+impl fmt::Display for Port {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Port::Auto => write!(f, "auto"),
+            Port::Fixed(port) => write!(f, "{}", port),
+        }
+    }
+}
+
+impl Serialize for Port {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Port::Auto => serializer.serialize_str("auto"),
+            Port::Fixed(port) => serializer.serialize_u16(*port),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Port {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(PortVisitor)
+    }
+}
+
+struct PortVisitor;
+
+impl<'de> Visitor<'de> for PortVisitor {
+    type Value = Port;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a port between 1 and 65535, or \"auto\"")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Port, E> {
+        match u16::try_from(value) {
+            Ok(port) if port != 0 => Ok(Port::Fixed(port)),
+            _ => Err(E::invalid_value(de::Unexpected::Unsigned(value), &self)),
+        }
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Port, E> {
+        match u64::try_from(value) {
+            Ok(value) => self.visit_u64(value),
+            Err(_) => Err(E::invalid_value(de::Unexpected::Signed(value), &self)),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Port, E> {
+        match value {
+            "auto" => Ok(Port::Auto),
+            _ => Err(E::invalid_value(de::Unexpected::Str(value), &self)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for (json, port) in [("\"auto\"", Port::Auto), ("18081", Port::Fixed(18081))] {
+            assert_eq!(serde_json::from_str::<Port>(json).unwrap(), port);
+            assert_eq!(serde_json::to_string(&port).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_ports() {
+        for json in ["0", "65536", "-1", "\"any\"", "null"] {
+            assert!(serde_json::from_str::<Port>(json).is_err(), "{}", json);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{Error, Result};
+use chrono::{FixedOffset, NaiveTime, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// Mining only during set hours of the week, such as when electricity is cheapest.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleConfig {
+    pub enabled: bool,
+    pub timezone: Timezone,
+    /// When mining is allowed. Outside of them, XMRig is paused.
+    pub windows: Vec<MiningWindow>,
+}
+
+impl ScheduleConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.enabled && self.windows.is_empty() {
+            return Err(Error::msg(
+                "The mining schedule has no windows, so XMRig would never mine",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct MiningWindow {
+    /// Days the window starts on. If left empty, every day.
+    #[serde(default)]
+    pub days: Vec<Day>,
+    #[serde(with = "time_of_day")]
+    pub start: NaiveTime,
+    /// If it's not after `start`, the window runs past midnight into the next day.
+    #[serde(with = "time_of_day")]
+    pub end: NaiveTime,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Day {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl From<Weekday> for Day {
+    fn from(day: Weekday) -> Self {
+        match day {
+            Weekday::Mon => Day::Monday,
+            Weekday::Tue => Day::Tuesday,
+            Weekday::Wed => Day::Wednesday,
+            Weekday::Thu => Day::Thursday,
+            Weekday::Fri => Day::Friday,
+            Weekday::Sat => Day::Saturday,
+            Weekday::Sun => Day::Sunday,
+        }
+    }
+}
+
+/// The time zone a schedule is in. Written as `"local"` for the system's own, as a time zone name
+/// such as `"Europe/London"`, both of which follow daylight saving time, or as an offset from UTC
+/// such as `"+01:00"`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(try_from = "String", into = "String")]
+pub enum Timezone {
+    #[default]
+    Local,
+    Named(Tz),
+    Fixed(FixedOffset),
+}
+
+impl fmt::Display for Timezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Timezone::Local => write!(f, "local"),
+            Timezone::Named(tz) => write!(f, "{}", tz.name()),
+            Timezone::Fixed(offset) => write!(f, "{}", offset),
+        }
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "local" => Ok(Timezone::Local),
+            "UTC" | "utc" | "Z" => Ok(Timezone::Fixed(FixedOffset::east_opt(0).unwrap())),
+            _ => s
+                .parse()
+                .map(Timezone::Fixed)
+                .or_else(|_| s.parse().map(Timezone::Named))
+                .map_err(|_| {
+                    Error::msg(format!(
+                        "'{}' isn't a time zone. Use \"local\", a name like \"Europe/London\", or an \
+                         offset from UTC like \"+01:00\"",
+                        s
+                    ))
+                }),
+        }
+    }
+}
+
+impl TryFrom<String> for Timezone {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Timezone> for String {
+    fn from(timezone: Timezone) -> Self {
+        timezone.to_string()
+    }
+}
+
+/// Times of day written as `"HH:MM"`.
+mod time_of_day {
+    use chrono::NaiveTime;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    const FORMAT: &str = "%H:%M";
+
+    pub fn serialize<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.format(FORMAT).to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        NaiveTime::parse_from_str(&s, FORMAT)
+            .map_err(|_| de::Error::custom(format!("'{}' isn't a time of day like \"22:30\"", s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_schedule() {
+        let schedule: ScheduleConfig = serde_json::from_str(
+            r#"{
+                "enabled": true,
+                "timezone": "+01:00",
+                "windows": [{"days": ["monday", "friday"], "start": "22:00", "end": "07:30"}]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            schedule.timezone,
+            Timezone::Fixed(FixedOffset::east_opt(3600).unwrap())
+        );
+        let window = &schedule.windows[0];
+        assert_eq!(window.days, [Day::Monday, Day::Friday]);
+        assert_eq!(window.end, NaiveTime::from_hms_opt(7, 30, 0).unwrap());
+
+        let written = serde_json::to_value(&schedule).unwrap();
+        assert_eq!(written["timezone"], "+01:00");
+        assert_eq!(written["windows"][0]["start"], "22:00");
+        assert_eq!(ScheduleConfig::default().timezone, Timezone::Local);
+
+        let named: Timezone = "America/New_York".parse().unwrap();
+        assert_eq!(named, Timezone::Named(Tz::America__New_York));
+        assert_eq!(named.to_string(), "America/New_York");
+    }
+
+    #[test]
+    fn rejects_invalid_schedules() {
+        for json in [
+            r#"{"timezone": "Mars/Olympus_Mons"}"#,
+            r#"{"windows": [{"start": "25:00", "end": "07:00"}]}"#,
+            r#"{"windows": [{"days": ["someday"], "start": "22:00", "end": "07:00"}]}"#,
+        ] {
+            assert!(
+                serde_json::from_str::<ScheduleConfig>(json).is_err(),
+                "{}",
+                json
+            );
+        }
+        let never = ScheduleConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        assert!(never.validate().is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use std::{default::Default, fmt};
+
+use anyhow::{Error, Result};
+use serde::{Deserialize, Serialize};
+
+use super::port::{self, Port};
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct XmrigConfig {
+    pub verbose: bool,
+    /// Bearer token for API access. If left blank, a secure token will be generated randomly.
+    pub bearer_token: Option<String>,
+    /// Port for XMRig's HTTP API.
+    pub api_port: Port,
+    pub privileges: XmrigPrivileges,
+    /// Number of mining threads. If left blank, XMRig chooses based on the CPU's cache.
+    pub threads: Option<u32>,
+    /// Percentage of the threads XMRig would choose to actually use, from 1 to 100. Can't be
+    /// combined with `threads`.
+    pub max_threads_hint: Option<u8>,
+    /// CPUs to mine on, as a bit mask: bit 0 for the first CPU, bit 1 for the second, and so on.
+    pub cpu_affinity: Option<u64>,
+    /// Priority of the mining threads, from 0 (idle) to 5 (highest). If left blank, XMRig leaves
+    /// it alone.
+    pub cpu_priority: Option<u8>,
+    /// Let other programs have the CPU when they need it, at a small cost to the hashrate.
+    pub cpu_yield: bool,
+    pub huge_pages: bool,
+    /// Put RandomX's dataset in 1GB huge pages. Needs XMRig to run as root, on Linux.
+    pub one_gb_pages: bool,
+    pub randomx_mode: RandomxMode,
+    /// Threads used to initialize RandomX's dataset. If left blank, all of them.
+    pub randomx_init: Option<u32>,
+    /// Tune the CPU's model-specific registers for RandomX.
+    pub msr_mod: bool,
+    /// Percentage of the time spent mining for XMRig's developers. If left blank, XMRig's default
+    /// is used.
+    pub donate_level: Option<u8>,
+    /// Give the mining threads L3 cache to themselves, on CPUs that support Cache QoS.
+    pub cache_qos: bool,
+}
+
+impl Default for XmrigConfig {
+    fn default() -> Self {
+        XmrigConfig {
+            verbose: false,
+            bearer_token: None,
+            api_port: Port::Fixed(port::XMRIG_API),
+            privileges: XmrigPrivileges::default(),
+            threads: None,
+            max_threads_hint: None,
+            cpu_affinity: None,
+            cpu_priority: None,
+            cpu_yield: true,
+            huge_pages: true,
+            one_gb_pages: false,
+            randomx_mode: RandomxMode::default(),
+            randomx_init: None,
+            msr_mod: true,
+            donate_level: None,
+            cache_qos: false,
+        }
+    }
+}
+
+impl XmrigConfig {
+    /// Check the settings before XMRig is started with them, so that mistakes are reported plainly
+    /// rather than buried in XMRig's output.
+    pub fn validate(&self) -> Result<()> {
+        if self.threads == Some(0) {
+            return Err(Error::msg(
+                "XMRig's thread count can't be 0. Leave it blank to let XMRig choose",
+            ));
+        }
+        if let Some(hint) = self.max_threads_hint {
+            if self.threads.is_some() {
+                return Err(Error::msg(
+                    "Set either XMRig's thread count or its max threads hint, not both",
+                ));
+            }
+            if !(1..=100).contains(&hint) {
+                return Err(Error::msg(
+                    "XMRig's max threads hint must be between 1 and 100",
+                ));
+            }
+        }
+        if self.cpu_affinity == Some(0) {
+            return Err(Error::msg(
+                "XMRig's CPU affinity must include at least one CPU",
+            ));
+        }
+        if matches!(self.cpu_priority, Some(priority) if priority > 5) {
+            return Err(Error::msg("XMRig's CPU priority must be between 0 and 5"));
+        }
+        if self.randomx_init == Some(0) {
+            return Err(Error::msg(
+                "RandomX can't be initialized with 0 threads. Leave it blank to use them all",
+            ));
+        }
+        if matches!(self.donate_level, Some(level) if level > 99) {
+            return Err(Error::msg("XMRig's donate level must be between 0 and 99"));
+        }
+        if self.one_gb_pages && self.privileges == XmrigPrivileges::Unprivileged {
+            return Err(Error::msg("1GB pages need XMRig to run as root"));
+        }
+        Ok(())
+    }
+}
+
+/// How XMRig gets the privileges it needs to reserve huge pages and tune the CPU's model-specific
+/// registers. Only matters on Linux and macOS.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum XmrigPrivileges {
+    /// Run XMRig as root through `pkexec`, asking for a password each time it starts.
+    #[default]
+    Root,
+    /// Run XMRig as the current user. Huge pages and the CPU are set up beforehand, once, by
+    /// `ministo --tune-system`.
+    Unprivileged,
+}
+
+/// How RandomX hashes: with its 2GB dataset, or with just the 256MB cache, which is several times
+/// slower.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum RandomxMode {
+    /// Fast if there's enough memory, light otherwise.
+    #[default]
+    Auto,
+    Fast,
+    Light,
+}
+
+impl fmt::Display for RandomxMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RandomxMode::Auto => write!(f, "auto"),
+            RandomxMode::Fast => write!(f, "fast"),
+            RandomxMode::Light => write!(f, "light"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_valid() {
+        XmrigConfig::default().validate().unwrap();
+        // Files written before these settings existed only have a few of them.
+        let config: XmrigConfig =
+            serde_json::from_str(r#"{"verbose": true, "bearerToken": null}"#).unwrap();
+        assert!(config.huge_pages && config.msr_mod && config.cpu_yield);
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        let invalid = [
+            XmrigConfig {
+                threads: Some(0),
+                ..Default::default()
+            },
+            XmrigConfig {
+                threads: Some(4),
+                max_threads_hint: Some(50),
+                ..Default::default()
+            },
+            XmrigConfig {
+                max_threads_hint: Some(101),
+                ..Default::default()
+            },
+            XmrigConfig {
+                cpu_affinity: Some(0),
+                ..Default::default()
+            },
+            XmrigConfig {
+                cpu_priority: Some(6),
+                ..Default::default()
+            },
+            XmrigConfig {
+                randomx_init: Some(0),
+                ..Default::default()
+            },
+            XmrigConfig {
+                donate_level: Some(100),
+                ..Default::default()
+            },
+            XmrigConfig {
+                one_gb_pages: true,
+                privileges: XmrigPrivileges::Unprivileged,
+                ..Default::default()
+            },
+        ];
+        for config in invalid {
+            assert!(config.validate().is_err(), "{:?}", config);
+        }
+    }
+}
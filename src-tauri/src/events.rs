@@ -0,0 +1,175 @@
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use log::{info, warn};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    monerod::{sync::SyncProgress, Status as MonerodStatus},
+    p2pool::api::Status as P2poolStatus,
+    schedule::ScheduleStatus,
+    startup::Phase,
+    supervisor::{Component, ProcessState},
+    xmrig::{api::Summary, ActivePool},
+};
+
+/// Everything Ministo reports while managing its processes.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A line written to stdout or stderr by one of the processes.
+    Output {
+        component: Component,
+        line: String,
+    },
+    /// A process was started, stopped, or crashed.
+    ProcessState {
+        component: Component,
+        state: ProcessState,
+    },
+    MonerodStatus(MonerodStatus),
+    /// How far monerod is from synchronizing the blockchain.
+    SyncProgress(SyncProgress),
+    XmrigStatus(Box<Summary>),
+    /// XMRig started mining on another pool, failing over or returning to the profile's pool.
+    ActivePool(ActivePool),
+    P2poolStatus(P2poolStatus),
+    /// Starting to mine moved on to another phase.
+    StartupPhase(Phase),
+    /// The schedule started or ended a mining window, or was overridden.
+    ScheduleStatus(ScheduleStatus),
+}
+
+impl Event {
+    /// Name of the event, as emitted to the frontend.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::Output { component, .. } => match component {
+                Component::Monerod => "monerod-stdout",
+                Component::P2pool => "p2pool-stdout",
+                Component::Xmrig => "xmrig-stdout",
+            },
+            Event::ProcessState { .. } => "process-state",
+            Event::MonerodStatus(_) => "monerod-status",
+            Event::SyncProgress(_) => "sync-progress",
+            Event::XmrigStatus(_) => "xmrig-status",
+            Event::ActivePool(_) => "active-pool",
+            Event::P2poolStatus(_) => "p2pool-status",
+            Event::StartupPhase(_) => "startup-phase",
+            Event::ScheduleStatus(_) => "schedule-status",
+        }
+    }
+
+    /// Payload of the event, as emitted to the frontend.
+    pub fn payload(&self) -> Value {
+        match self {
+            Event::Output { line, .. } => json!(line),
+            Event::ProcessState { component, state } => {
+                let mut payload = json!(state);
+                payload["component"] = json!(component);
+                payload
+            }
+            Event::MonerodStatus(status) => json!(status),
+            Event::SyncProgress(progress) => json!(progress),
+            Event::XmrigStatus(status) => json!(status),
+            Event::ActivePool(pool) => json!(pool),
+            Event::P2poolStatus(status) => json!(status),
+            Event::StartupPhase(phase) => json!(phase),
+            Event::ScheduleStatus(status) => json!(status),
+        }
+    }
+}
+
+/// Consumer of the events Ministo publishes.
+pub trait MinistoEvents: Send + Sync {
+    fn publish(&self, event: &Event);
+}
+
+/// Forwards every event to each of its subscribers, so that several consumers can follow along.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: RwLock<Vec<Arc<dyn MinistoEvents>>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&self, subscriber: Arc<dyn MinistoEvents>) {
+        self.subscribers
+            .write()
+            .expect("event subscribers lock poisoned")
+            .push(subscriber);
+    }
+}
+
+impl MinistoEvents for EventBus {
+    fn publish(&self, event: &Event) {
+        let subscribers = self
+            .subscribers
+            .read()
+            .expect("event subscribers lock poisoned");
+        for subscriber in subscribers.iter() {
+            subscriber.publish(event);
+        }
+    }
+}
+
+impl fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let subscribers = self.subscribers.read().map(|s| s.len()).unwrap_or_default();
+        f.debug_struct("EventBus")
+            .field("subscribers", &subscribers)
+            .finish()
+    }
+}
+
+/// Forwards events to the frontend.
+pub struct TauriEvents(pub AppHandle);
+
+impl MinistoEvents for TauriEvents {
+    fn publish(&self, event: &Event) {
+        let payload = match event {
+            Event::Output { line, .. } => {
+                let html = ansi_to_html::convert_escaped(line).unwrap_or_else(|_| line.clone());
+                json!(html + "</br>")
+            }
+            _ => event.payload(),
+        };
+        self.0
+            .emit_all(event.name(), payload)
+            .unwrap_or_else(|e| warn!("Failed to emit {} event: {}", event.name(), e));
+    }
+}
+
+/// Writes events to the log, for use when running without a window.
+pub struct LogEvents;
+
+impl MinistoEvents for LogEvents {
+    fn publish(&self, event: &Event) {
+        match event {
+            Event::Output { component, line } => info!("[{}] {}", component, line),
+            _ => info!("{}: {}", event.name(), event.payload()),
+        }
+    }
+}
+
+/// Keeps every event in memory, so that tests can inspect what was published.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryEvents {
+    events: std::sync::Mutex<Vec<Event>>,
+}
+
+#[cfg(test)]
+impl MemoryEvents {
+    pub fn events(&self) -> Vec<Event> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl MinistoEvents for MemoryEvents {
+    fn publish(&self, event: &Event) {
+        self.events.lock().unwrap().push(event.clone());
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use log::info;
+use tokio::signal::ctrl_c;
+
+use crate::{events::LogEvents, schedule::watch_schedule, start_all, stop_all, MinistoState};
+
+/// Run Ministo without a window, logging events instead of displaying them. Mining continues until
+/// SIGINT or SIGTERM is received.
+pub async fn run(state: MinistoState) -> Result<()> {
+    state.events.subscribe(Arc::new(LogEvents));
+    // Waiting for monerod to synchronize can take hours, so listen for signals meanwhile.
+    tokio::select! {
+        res = start_all(&state) => {
+            if let Err(e) = res {
+                stop_all(&state).await;
+                return Err(e);
+            }
+            // Keep to the schedule until told to stop.
+            tokio::select! {
+                res = shutdown_signal() => res?,
+                _ = watch_schedule(&state) => {}
+            }
+        }
+        res = shutdown_signal() => res?,
+    }
+    info!("Shutting down");
+    stop_all(&state).await;
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn shutdown_signal() -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        res = ctrl_c() => res?,
+        _ = terminate.recv() => {}
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() -> Result<()> {
+    ctrl_c().await?;
+    Ok(())
+}
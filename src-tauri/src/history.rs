@@ -0,0 +1,388 @@
+use std::{
+    path::Path,
+    sync::{
+        mpsc::{sync_channel, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Error, Result};
+use log::{debug, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{command, State};
+
+use crate::{
+    events::{Event, MinistoEvents},
+    MinistoState,
+};
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Resolutions samples are kept at, in seconds, and how long each is kept for. Samples arrive
+/// every 8 seconds, so the finest resolution holds roughly one sample per bucket.
+const TIERS: [(u64, u64); 3] = [(10, DAY), (5 * 60, 30 * DAY), (60 * 60, 2 * 365 * DAY)];
+
+/// How often expired samples are deleted.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Samples waiting to be written. A few minutes' worth, in case the disk is slow.
+const QUEUE_LENGTH: usize = 256;
+
+/// Something Ministo keeps a history of.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Metric {
+    /// XMRig's hashrate over the last 10 seconds, in H/s.
+    Hashrate,
+    /// Shares accepted by the pool since XMRig started.
+    SharesAccepted,
+    /// Shares rejected by the pool since XMRig started.
+    SharesRejected,
+    /// Height of the local blockchain.
+    DaemonHeight,
+    /// Shares found on the P2Pool sidechain since P2Pool started.
+    P2poolShares,
+}
+
+impl Metric {
+    fn name(&self) -> &'static str {
+        match self {
+            Metric::Hashrate => "hashrate",
+            Metric::SharesAccepted => "sharesAccepted",
+            Metric::SharesRejected => "sharesRejected",
+            Metric::DaemonHeight => "daemonHeight",
+            Metric::P2poolShares => "p2poolShares",
+        }
+    }
+}
+
+/// The samples of a metric within one bucket of time.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Point {
+    /// Start of the bucket, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub average: f64,
+    pub maximum: f64,
+}
+
+/// Time-series store of mining and sync statistics, downsampled as they age.
+#[derive(Debug)]
+pub struct History {
+    conn: Mutex<Connection>,
+    last_pruned: Mutex<u64>,
+}
+
+impl History {
+    /// Open the history database at `path`, creating it if needed.
+    pub fn open(path: &Path) -> Result<History> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+            PRAGMA synchronous = NORMAL;
+            CREATE TABLE IF NOT EXISTS samples (
+                metric TEXT NOT NULL,
+                resolution INTEGER NOT NULL,
+                timestamp INTEGER NOT NULL,
+                sum REAL NOT NULL,
+                count INTEGER NOT NULL,
+                maximum REAL NOT NULL,
+                PRIMARY KEY (metric, resolution, timestamp)
+            ) WITHOUT ROWID;",
+        )?;
+        Ok(History {
+            conn: Mutex::new(conn),
+            last_pruned: Mutex::new(0),
+        })
+    }
+
+    /// Record `value` as the value of `metric` at `timestamp`, in seconds since the Unix epoch.
+    fn record_at(&self, metric: Metric, timestamp: u64, value: f64) -> Result<()> {
+        let mut conn = self.conn.lock().expect("history lock poisoned");
+        let tx = conn.transaction()?;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO samples (metric, resolution, timestamp, sum, count, maximum)
+                VALUES (?1, ?2, ?3, ?4, 1, ?4)
+                ON CONFLICT (metric, resolution, timestamp) DO UPDATE SET
+                    sum = sum + excluded.sum,
+                    count = count + 1,
+                    maximum = max(maximum, excluded.maximum)",
+            )?;
+            for (resolution, _) in TIERS {
+                let bucket = timestamp - timestamp % resolution;
+                insert.execute(params![metric.name(), resolution, bucket, value])?;
+            }
+        }
+        tx.commit()?;
+
+        let mut last_pruned = self.last_pruned.lock().expect("history lock poisoned");
+        if timestamp >= *last_pruned + PRUNE_INTERVAL.as_secs() {
+            prune(&conn, timestamp)?;
+            *last_pruned = timestamp;
+        }
+        Ok(())
+    }
+
+    /// Samples of `metric` between `from` and `to`, in seconds since the Unix epoch. Uses the
+    /// finest resolution still kept for `from`, unless that would return more than `max_points`.
+    pub fn query(&self, metric: Metric, from: u64, to: u64, max_points: u64) -> Result<Vec<Point>> {
+        self.query_at(metric, from, to, max_points, unix_time())
+    }
+
+    fn query_at(
+        &self,
+        metric: Metric,
+        from: u64,
+        to: u64,
+        max_points: u64,
+        now: u64,
+    ) -> Result<Vec<Point>> {
+        let resolution = TIERS
+            .iter()
+            .find(|(resolution, retention)| {
+                from.saturating_add(*retention) >= now
+                    && to.saturating_sub(from) / resolution <= max_points
+            })
+            .unwrap_or(&TIERS[TIERS.len() - 1])
+            .0;
+
+        let conn = self.conn.lock().expect("history lock poisoned");
+        let mut select = conn.prepare_cached(
+            "SELECT timestamp, sum / count, maximum FROM samples
+            WHERE metric = ?1 AND resolution = ?2 AND timestamp >= ?3 AND timestamp <= ?4
+            ORDER BY timestamp",
+        )?;
+        let from_bucket = from - from % resolution;
+        let points = select
+            .query_map(params![metric.name(), resolution, from_bucket, to], |row| {
+                Ok(Point {
+                    timestamp: row.get(0)?,
+                    average: row.get(1)?,
+                    maximum: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(points)
+    }
+}
+
+/// Delete samples older than their resolution is kept for.
+fn prune(conn: &Connection, now: u64) -> Result<()> {
+    for (resolution, retention) in TIERS {
+        conn.execute(
+            "DELETE FROM samples WHERE resolution = ?1 AND timestamp < ?2",
+            params![resolution, now.saturating_sub(retention)],
+        )?;
+    }
+    Ok(())
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Records the statistics in published events. Samples are written to the database on a thread of
+/// their own, so that publishing an event never waits on the disk.
+pub struct HistoryRecorder {
+    samples: SyncSender<(Metric, u64, f64)>,
+}
+
+impl HistoryRecorder {
+    pub fn new(history: Arc<History>) -> Result<HistoryRecorder> {
+        let (samples, queue) = sync_channel::<(Metric, u64, f64)>(QUEUE_LENGTH);
+        thread::Builder::new()
+            .name("history".to_string())
+            .spawn(move || {
+                // Runs until the recorder is dropped.
+                for (metric, timestamp, value) in queue {
+                    if let Err(e) = history.record_at(metric, timestamp, value) {
+                        warn!("Failed to record {} history: {}", metric.name(), e);
+                    }
+                }
+            })?;
+        Ok(HistoryRecorder { samples })
+    }
+}
+
+impl MinistoEvents for HistoryRecorder {
+    fn publish(&self, event: &Event) {
+        let samples = match event {
+            Event::XmrigStatus(summary) => {
+                let mut samples = vec![
+                    (Metric::SharesAccepted, summary.connection.accepted as f64),
+                    (Metric::SharesRejected, summary.connection.rejected as f64),
+                ];
+                if let Some(Some(hashrate)) = summary.hashrate.total.first() {
+                    samples.push((Metric::Hashrate, *hashrate));
+                }
+                samples
+            }
+            Event::SyncProgress(progress) => vec![(Metric::DaemonHeight, progress.height as f64)],
+            Event::P2poolStatus(status) => vec![(Metric::P2poolShares, status.shares_found as f64)],
+            _ => return,
+        };
+        let timestamp = unix_time();
+        for (metric, value) in samples {
+            match self.samples.try_send((metric, timestamp, value)) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    debug!(
+                        "History is falling behind; dropped a {} sample",
+                        metric.name()
+                    )
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    warn!("Failed to record {} history: writer stopped", metric.name())
+                }
+            }
+        }
+    }
+}
+
+/// History of `metric` between `from` and `to`, in seconds since the Unix epoch.
+#[command(async)]
+pub async fn query_history(
+    state: State<'_, MinistoState>,
+    metric: Metric,
+    from: u64,
+    to: u64,
+    max_points: Option<u64>,
+) -> Result<Vec<Point>, String> {
+    let history = state
+        .history
+        .clone()
+        .ok_or_else(|| "History is unavailable".to_string())?;
+    // SQLite blocks, so keep it off the async runtime's threads.
+    tauri::async_runtime::spawn_blocking(move || {
+        history.query(metric, from, to, max_points.unwrap_or(1000))
+    })
+    .await
+    .map_err(Error::new)
+    .and_then(|res| res)
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::monerod::sync::SyncProgress;
+
+    use tempfile::tempdir;
+
+    const NOW: u64 = 1_700_000_000;
+
+    #[test]
+    fn averages_samples_within_a_bucket() {
+        let dir = tempdir().unwrap();
+        let history = History::open(&dir.path().join("history.db")).unwrap();
+
+        let start = NOW - NOW % 3600;
+        history.record_at(Metric::Hashrate, start, 100.0).unwrap();
+        history
+            .record_at(Metric::Hashrate, start + 8, 200.0)
+            .unwrap();
+        history
+            .record_at(Metric::Hashrate, start + 16, 600.0)
+            .unwrap();
+        history.record_at(Metric::DaemonHeight, start, 5.0).unwrap();
+
+        let raw = history
+            .query_at(Metric::Hashrate, start, start + 60, 1000, start + 60)
+            .unwrap();
+        assert_eq!(raw.len(), 2);
+        assert_eq!(raw[0].average, 150.0);
+        assert_eq!(raw[0].maximum, 200.0);
+        assert_eq!(raw[1].timestamp, start + 10);
+
+        let hourly = history
+            .query_at(Metric::Hashrate, start, start + 60 * DAY, 1000, start + 60)
+            .unwrap();
+        assert_eq!(
+            hourly,
+            vec![Point {
+                timestamp: start,
+                average: 300.0,
+                maximum: 600.0,
+            }]
+        );
+    }
+
+    #[test]
+    fn uses_coarser_resolutions_for_older_ranges() {
+        let dir = tempdir().unwrap();
+        let history = History::open(&dir.path().join("history.db")).unwrap();
+
+        let start = NOW - NOW % 3600;
+        history.record_at(Metric::Hashrate, start, 100.0).unwrap();
+        history
+            .record_at(Metric::Hashrate, start + 8, 200.0)
+            .unwrap();
+
+        // Ten seconds samples are only kept for a day.
+        let now = start + 2 * DAY;
+        let points = history
+            .query_at(Metric::Hashrate, start, now, 1000, now)
+            .unwrap();
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].average, 150.0);
+    }
+
+    #[test]
+    fn records_published_events_in_the_background() {
+        let dir = tempdir().unwrap();
+        let history = Arc::new(History::open(&dir.path().join("history.db")).unwrap());
+        let recorder = HistoryRecorder::new(history.clone()).unwrap();
+        recorder.publish(&Event::SyncProgress(SyncProgress {
+            height: 1234,
+            target_height: 2000,
+            percent: 61.7,
+            blocks_per_second: None,
+            eta_secs: None,
+            database_size: 0,
+            free_space: 0,
+        }));
+
+        let now = unix_time();
+        for _ in 0..100 {
+            let points = history
+                .query(Metric::DaemonHeight, now - 60, now + 60, 1000)
+                .unwrap();
+            if let Some(point) = points.first() {
+                assert_eq!(point.average, 1234.0);
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("the sample was never written");
+    }
+
+    #[test]
+    fn prunes_expired_samples() {
+        let dir = tempdir().unwrap();
+        let history = History::open(&dir.path().join("history.db")).unwrap();
+
+        let start = NOW - NOW % 3600;
+        history.record_at(Metric::Hashrate, start, 100.0).unwrap();
+        history
+            .record_at(Metric::Hashrate, start + 2 * DAY, 200.0)
+            .unwrap();
+
+        let conn = history.conn.lock().unwrap();
+        let count: u64 = conn
+            .query_row(
+                "SELECT count(*) FROM samples WHERE resolution = 10",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+}
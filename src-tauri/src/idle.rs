@@ -0,0 +1,252 @@
+use std::time::Duration;
+#[cfg(target_os = "linux")]
+use std::{
+    env,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use log::{info, warn};
+use tokio::time::interval;
+
+use crate::{config::IdleConfig, xmrig::reconfigure_xmrig, MinistoState};
+
+/// How often to check whether the user is idle.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How mining is held back while the user is active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Restraint {
+    None,
+    Paused,
+    /// Mining with only this many threads.
+    Throttled(u32),
+}
+
+impl Restraint {
+    /// How mining should be held back, after the user has been idle for `idle`.
+    fn wanted(settings: &IdleConfig, idle: Duration) -> Restraint {
+        if !settings.enabled || idle >= Duration::from_secs(settings.idle_secs) {
+            return Restraint::None;
+        }
+        match settings.active_threads {
+            Some(threads) if threads > 0 => Restraint::Throttled(threads),
+            _ => Restraint::Paused,
+        }
+    }
+}
+
+/// Hold XMRig back while the user is active, and let it mine at full speed once they've been idle
+/// for a while, as set in the active profile. Runs until Ministo exits.
+pub async fn watch_idle(state: &MinistoState) {
+    let mut interval = interval(POLL_INTERVAL);
+    let mut restraint = Restraint::None;
+    let mut warned = false;
+    loop {
+        interval.tick().await;
+        if !state.xmrig.process.is_running().await {
+            // XMRig starts over at full speed.
+            restraint = Restraint::None;
+            state.xmrig.set_active_threads(None);
+            continue;
+        }
+
+        let settings = state.config.lock().await.profile().idle;
+        let idle = if settings.enabled {
+            match tauri::async_runtime::spawn_blocking(idle_time)
+                .await
+                .ok()
+                .flatten()
+            {
+                Some(idle) => idle,
+                None => {
+                    if !warned {
+                        warn!("Can't tell whether the user is idle, so mining isn't held back");
+                        warned = true;
+                    }
+                    continue;
+                }
+            }
+        } else {
+            Duration::ZERO
+        };
+        let wanted = Restraint::wanted(&settings, idle);
+
+        // XMRig may have been restarted, or resumed by hand, since it was paused.
+        if restraint == Restraint::Paused
+            && matches!(state.xmrig.api.summary().await, Ok(summary) if !summary.paused)
+        {
+            restraint = Restraint::None;
+        }
+        if wanted == restraint {
+            continue;
+        }
+        match restrain(state, restraint, wanted).await {
+            Ok(()) => {
+                match wanted {
+                    Restraint::None => info!("User is idle; mining at full speed"),
+                    Restraint::Paused => info!("User is active; pausing mining"),
+                    Restraint::Throttled(threads) => {
+                        info!("User is active; mining with {} threads", threads)
+                    }
+                }
+                restraint = wanted;
+            }
+            Err(e) => warn!("Failed to adjust mining to the user's activity: {}", e),
+        }
+    }
+}
+
+/// Go from holding mining back as in `from`, to as in `to`.
+async fn restrain(state: &MinistoState, from: Restraint, to: Restraint) -> Result<()> {
+    // Outside of the mining windows, the schedule keeps XMRig paused.
+    if from == Restraint::Paused && state.schedule.allows_mining() {
+        state.xmrig.api.resume().await?;
+    }
+    let threads = match to {
+        Restraint::Throttled(threads) => Some(threads),
+        _ => None,
+    };
+    if threads != state.xmrig.active_threads() {
+        state.xmrig.set_active_threads(threads);
+        reconfigure_xmrig(state).await?;
+    }
+    if to == Restraint::Paused {
+        state.xmrig.api.pause().await?;
+    }
+    Ok(())
+}
+
+/// How long the user has been idle, from whichever source this desktop supports: GNOME's idle
+/// monitor (on Wayland or X11), X11's screen saver extension through `xprintidle`, or logind's
+/// idle hint.
+#[cfg(target_os = "linux")]
+fn idle_time() -> Option<Duration> {
+    let output = |program: &str, args: &[&str]| -> Option<String> {
+        let output = Command::new(program).args(args).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        String::from_utf8(output.stdout).ok()
+    };
+
+    let mutter = || {
+        output(
+            "gdbus",
+            &[
+                "call",
+                "--session",
+                "--dest",
+                "org.gnome.Mutter.IdleMonitor",
+                "--object-path",
+                "/org/gnome/Mutter/IdleMonitor/Core",
+                "--method",
+                "org.gnome.Mutter.IdleMonitor.GetIdletime",
+            ],
+        )
+        .and_then(|out| parse_mutter(&out))
+    };
+    let xprintidle = || output("xprintidle", &[]).and_then(|out| parse_millis(&out));
+    let logind = || {
+        let session = env::var("XDG_SESSION_ID").unwrap_or_else(|_| "self".to_string());
+        let out = output(
+            "loginctl",
+            &[
+                "show-session",
+                &session,
+                "-p",
+                "IdleHint",
+                "-p",
+                "IdleSinceHint",
+            ],
+        )?;
+        parse_logind(&out, SystemTime::now().duration_since(UNIX_EPOCH).ok()?)
+    };
+    mutter().or_else(xprintidle).or_else(logind)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn idle_time() -> Option<Duration> {
+    None
+}
+
+/// Idle time from GNOME's idle monitor, which replies like `(uint64 12345,)`.
+#[cfg(any(target_os = "linux", test))]
+fn parse_mutter(output: &str) -> Option<Duration> {
+    let millis = output.trim().strip_prefix("(uint64 ")?.strip_suffix(",)")?;
+    parse_millis(millis)
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn parse_millis(output: &str) -> Option<Duration> {
+    Some(Duration::from_millis(output.trim().parse().ok()?))
+}
+
+/// Idle time from logind's session properties, given the time since the epoch `now`.
+#[cfg(any(target_os = "linux", test))]
+fn parse_logind(output: &str, now: Duration) -> Option<Duration> {
+    let property = |name: &str| {
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix('='))
+    };
+    match property("IdleHint")? {
+        "yes" => {
+            // In microseconds since the epoch.
+            let since: u64 = property("IdleSinceHint")?.parse().ok()?;
+            Some(now.saturating_sub(Duration::from_micros(since)))
+        }
+        _ => Some(Duration::ZERO),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restraints() {
+        let mut settings = IdleConfig {
+            enabled: true,
+            idle_secs: 60,
+            active_threads: None,
+        };
+        let active = Duration::from_secs(10);
+        let idle = Duration::from_secs(60);
+        assert_eq!(Restraint::wanted(&settings, active), Restraint::Paused);
+        assert_eq!(Restraint::wanted(&settings, idle), Restraint::None);
+
+        settings.active_threads = Some(2);
+        assert_eq!(
+            Restraint::wanted(&settings, active),
+            Restraint::Throttled(2)
+        );
+        settings.active_threads = Some(0);
+        assert_eq!(Restraint::wanted(&settings, active), Restraint::Paused);
+
+        settings.enabled = false;
+        assert_eq!(Restraint::wanted(&settings, active), Restraint::None);
+    }
+
+    #[test]
+    fn idle_time_sources() {
+        assert_eq!(
+            parse_mutter("(uint64 12345,)\n"),
+            Some(Duration::from_millis(12345))
+        );
+        assert_eq!(parse_mutter("Error: no such service"), None);
+        assert_eq!(parse_millis("500\n"), Some(Duration::from_millis(500)));
+
+        let now = Duration::from_secs(1_700_000_100);
+        assert_eq!(
+            parse_logind("IdleHint=yes\nIdleSinceHint=1700000000000000\n", now),
+            Some(Duration::from_secs(100))
+        );
+        assert_eq!(
+            parse_logind("IdleHint=no\nIdleSinceHint=0\n", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_logind("", now), None);
+    }
+}
@@ -3,44 +3,157 @@
     windows_subsystem = "windows"
 )]
 
+mod address;
 mod config;
+mod events;
+mod headless;
+mod history;
+mod idle;
 mod monerod;
 mod p2pool;
+mod ports;
+mod preflight;
+mod profiles;
+mod schedule;
 mod settings;
+mod startup;
+mod supervisor;
 mod xmrig;
 
 use std::{
+    fs,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, Mutex as StdMutex},
 };
 
+use anyhow::{Error, Result};
 use clap::Arg;
-use log::{error, info};
-use tauri::{command, Manager, RunEvent, State, Window};
-use tokio::{join, sync::Mutex};
+use env_logger::Env;
+use log::{error, info, warn};
+use serde::Serialize;
+use tauri::{api::dialog::ask, command, AppHandle, Manager, RunEvent, State};
+use tokio::sync::Mutex;
 
-use config::{default_configuraton_dir, Config};
-use monerod::{start_monerod, MonerodState};
-use p2pool::start_p2pool;
+use address::validate_address;
+use config::{data_dir, default_configuraton_dir, Config, ConfigError};
+use events::{EventBus, TauriEvents};
+use history::{query_history, History, HistoryRecorder};
+use idle::watch_idle;
+use monerod::{
+    monerod_connections, monerod_last_block_header, monerod_set_bans, monerod_sync_info,
+    stop_monerod, MonerodState,
+};
+use p2pool::{stop_p2pool, P2poolState};
+use ports::{allocate_ports, Ports};
+use preflight::{preflight, preflight_check, PreflightError, PreflightReport, Severity};
+use profiles::{activate_profile, clone_profile, create_profile, delete_profile, list_profiles};
+use schedule::{schedule_status, watch_schedule, Schedule};
 use settings::{get_config, save_settings, select_blockchain_folder};
-use xmrig::{kill_xmrig, pause_mining, resume_mining, start_xmrig, XmrigState};
+use startup::{launched, set_phase, start_in_order, startup_phase, Phase, Startup};
+use supervisor::{Component, StopResult};
+use xmrig::{
+    pause_mining, resume_mining, stop_xmrig,
+    tuning::{self, tune_system},
+    xmrig_backends, xmrig_config, XmrigState,
+};
 
+/// Start mining in the background, returning once the pre-flight checks have passed. Progress is
+/// published as `startup-phase` events.
 #[command(async)]
-async fn start_mining(window: Window, state: State<'_, MinistoState>) -> Result<(), String> {
-    let (_, res, _) = join!(
-        start_monerod(window.clone(), state.clone()),
-        start_p2pool(window.clone(), state.clone()),
-        start_xmrig(window, state)
-    );
-    res.map_err(|e| e.to_string())?;
-
-    Ok(())
+async fn start_mining(
+    app: AppHandle,
+    state: State<'_, MinistoState>,
+) -> Result<PreflightReport, StartError> {
+    let report = prepare_start(&state).await?;
+    tauri::async_runtime::spawn(async move {
+        // Errors are published as the `failed` phase.
+        let _ = start_in_order(&app.state::<MinistoState>(), &ALL_COMPONENTS).await;
+    });
+    Ok(report)
+}
+
+/// Why mining couldn't be started, as reported to the frontend.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StartError {
+    message: String,
+    /// The pre-flight checks, if they're why mining didn't start.
+    preflight: Option<PreflightReport>,
+}
+
+impl From<Error> for StartError {
+    fn from(e: Error) -> Self {
+        StartError {
+            message: e.to_string(),
+            preflight: e
+                .downcast_ref::<PreflightError>()
+                .map(|PreflightError(report)| report.clone()),
+        }
+    }
+}
+
+const ALL_COMPONENTS: [Component; 3] = [Component::Monerod, Component::P2pool, Component::Xmrig];
+
+/// Start every process, publishing their output and status to `state.events`. Nothing is started
+/// unless the pre-flight checks pass, and each process waits for the one it relies on to be ready.
+async fn start_all(state: &MinistoState) -> Result<PreflightReport> {
+    let report = prepare_start(state).await?;
+    start_in_order(state, &ALL_COMPONENTS).await?;
+    Ok(report)
+}
+
+/// Check that mining can start, and choose the ports to start with.
+async fn prepare_start(state: &MinistoState) -> Result<PreflightReport> {
+    if state.monerod.process.is_active().await
+        || state.p2pool.process.is_active().await
+        || state.xmrig.process.is_active().await
+    {
+        return Err(Error::msg(
+            "Already mining. Stop mining before starting again.",
+        ));
+    }
+
+    let report = preflight(state).await;
+    for check in report.with_severity(Severity::Warning) {
+        warn!("{}", check.message);
+    }
+    if !report.passed() {
+        return Err(PreflightError(report).into());
+    }
+
+    let launched = launched(state.config.lock().await.profile());
+    allocate_ports(state, &launched).await?;
+    Ok(report)
+}
+
+#[command(async)]
+async fn stop_mining(state: State<'_, MinistoState>) -> Result<StopReport, String> {
+    Ok(stop_all(&state).await)
+}
+
+/// Stop every process, in reverse dependency order: XMRig mines on P2Pool, which relies on monerod.
+async fn stop_all(state: &MinistoState) -> StopReport {
+    // Don't let a startup in progress start anything behind our back.
+    state.startup.cancel().await;
+    let report = StopReport {
+        xmrig: stop_xmrig(&state.xmrig).await,
+        p2pool: stop_p2pool(&state.p2pool).await,
+        monerod: stop_monerod(&state.monerod).await,
+    };
+    set_phase(state, Phase::Stopped);
+    info!("Stopped mining: {:?}", report);
+    report
+}
+
+#[derive(Serialize, Debug)]
+pub struct StopReport {
+    xmrig: StopResult,
+    p2pool: StopResult,
+    monerod: StopResult,
 }
 
 #[tokio::main]
 async fn main() {
-    env_logger::init();
-
     let matches = clap::Command::new("Ministo")
         .about("A performant and user-friendly Monero mining interface")
         .arg(
@@ -49,22 +162,143 @@ async fn main() {
                 .takes_value(true)
                 .help("Path to your 'ministo.json' configuration file"),
         )
+        .arg(
+            Arg::new("headless")
+                .long("headless")
+                .help("Mine without opening a window, until SIGINT or SIGTERM is received"),
+        )
+        .arg(
+            Arg::new("restore-config")
+                .long("restore-config")
+                .help("If 'ministo.json' can't be read, replace it with its last good backup"),
+        )
+        .arg(
+            Arg::new("tune-system")
+                .long("tune-system")
+                .help("Reserve huge pages and tune the CPU for XMRig, then exit. Needs root"),
+        )
         .get_matches();
+    let headless = matches.is_present("headless");
+
+    if matches.is_present("tune-system") {
+        match tuning::tune() {
+            Ok(done) => {
+                for line in done {
+                    println!("{}", line);
+                }
+                return;
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if headless {
+        // Without a window, the log is the only way to follow what's happening.
+        env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    } else {
+        env_logger::init();
+    }
+
     let default_config_path = default_configuraton_dir().to_string_lossy().into_owned();
     let config_path = Path::new(matches.value_of("config").unwrap_or(&default_config_path));
 
+    if headless {
+        let state = MinistoState::new(config_path.to_path_buf());
+        if let Some(e) = state.config_error() {
+            if !matches.is_present("restore-config") {
+                error!(
+                    "Not mining with an unreadable configuration ({}). Fix it, or run with \
+                    --restore-config to restore the last good backup",
+                    e
+                );
+                std::process::exit(1);
+            }
+            if let Err(e) = state.restore_config().await {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        if let Err(e) = headless::run(state).await {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     #[cfg_attr(not(debug_assertions), allow(unused_variables))]
     tauri::Builder::default()
         .manage(MinistoState::new(config_path.to_path_buf()))
         .invoke_handler(tauri::generate_handler![
             start_mining,
+            stop_mining,
             pause_mining,
             resume_mining,
+            xmrig_backends,
+            xmrig_config,
+            monerod_sync_info,
+            monerod_last_block_header,
+            monerod_connections,
+            monerod_set_bans,
+            query_history,
+            preflight_check,
+            tune_system,
+            startup_phase,
+            schedule_status,
+            list_profiles,
+            create_profile,
+            clone_profile,
+            delete_profile,
+            activate_profile,
             select_blockchain_folder,
             save_settings,
+            validate_address,
             get_config
         ])
         .setup(|app| {
+            let state = app.state::<MinistoState>();
+            state.events.subscribe(Arc::new(TauriEvents(app.handle())));
+
+            // Idle mining only matters with someone at the desktop, so headless mode goes without.
+            let app_handle = app.handle();
+            tauri::async_runtime::spawn(async move {
+                watch_idle(&app_handle.state::<MinistoState>()).await;
+            });
+            let app_handle = app.handle();
+            tauri::async_runtime::spawn(async move {
+                watch_schedule(&app_handle.state::<MinistoState>()).await;
+            });
+
+            if let Some(e) = state.config_error() {
+                let app_handle = app.handle();
+                ask(
+                    app.get_window("main").as_ref(),
+                    "Ministo",
+                    format!(
+                        "Your settings could not be read: {}\n\nRestore the last good backup?",
+                        e
+                    ),
+                    move |restore| {
+                        if restore {
+                            tauri::async_runtime::spawn(async move {
+                                let state = app_handle.state::<MinistoState>();
+                                match state.restore_config().await {
+                                    // Reload so that the frontend picks up the restored settings.
+                                    Ok(()) => {
+                                        if let Some(window) = app_handle.get_window("main") {
+                                            let _ = window.eval("location.reload()");
+                                        }
+                                    }
+                                    Err(e) => error!("{}", e),
+                                }
+                            });
+                        }
+                    },
+                );
+            }
+
             #[cfg(debug_assertions)]
             app.get_window("main").unwrap().open_devtools();
             Ok(())
@@ -72,16 +306,14 @@ async fn main() {
         .build(tauri::generate_context!())
         .expect("error while building tauri application")
         .run(|app_handle, e| {
-            if let RunEvent::ExitRequested { .. } = e {
-                // Kill XMRig on exit.
-                let xmrig_state = app_handle.state::<MinistoState>().xmrig.clone();
+            if let RunEvent::ExitRequested { api, .. } = e {
+                // Shut everything down cleanly before exiting, so that monerod's database isn't
+                // left in an inconsistent state.
+                api.prevent_exit();
+                let app_handle = app_handle.clone();
                 tokio::spawn(async move {
-                    if xmrig_state.child.lock().await.is_some() {
-                        info!("Stopping XMRig");
-                        kill_xmrig(&xmrig_state.clone())
-                            .await
-                            .unwrap_or_else(|e| error!("{}", e));
-                    }
+                    stop_all(&app_handle.state::<MinistoState>()).await;
+                    app_handle.exit(0);
                 });
             }
         })
@@ -90,16 +322,25 @@ async fn main() {
 #[derive(Debug)]
 pub struct MinistoState {
     xmrig: Arc<XmrigState>,
+    p2pool: Arc<P2poolState>,
     monerod: Arc<MonerodState>,
     config: Arc<Mutex<Config>>,
     config_path: PathBuf,
+    /// Why the configuration file couldn't be read, if it couldn't.
+    config_error: StdMutex<Option<String>>,
+    /// Ports our processes were last started with.
+    ports: StdMutex<Ports>,
+    startup: Startup,
+    schedule: Schedule,
+    events: Arc<EventBus>,
+    history: Option<Arc<History>>,
 }
 
 impl MinistoState {
     pub fn new(config_path: PathBuf) -> MinistoState {
-        let config = match Config::open(&config_path) {
-            Ok(c) => c,
-            Err(_) => {
+        let (config, config_error) = match Config::open(&config_path) {
+            Ok(c) => (c, None),
+            Err(ConfigError::NotFound(_)) => {
                 info!(
                     "A 'ministo.json' file could not be found in {}; creating ministo.json",
                     config_path.display()
@@ -108,14 +349,69 @@ impl MinistoState {
                 config
                     .save(&config_path)
                     .expect("failed to create configuration file");
-                config
+                (config, None)
+            }
+            Err(e) => {
+                // Leave the file alone until the user decides whether to restore a backup.
+                error!("{}", e);
+                (Config::default(), Some(e.to_string()))
+            }
+        };
+
+        let events = Arc::new(EventBus::default());
+        let data_dir = data_dir(&config_path);
+        if let Err(e) = fs::create_dir_all(&data_dir) {
+            warn!("Failed to create {}: {}", data_dir.display(), e);
+        }
+        let history = match History::open(&data_dir.join("history.db")) {
+            Ok(history) => {
+                let history = Arc::new(history);
+                match HistoryRecorder::new(history.clone()) {
+                    Ok(recorder) => events.subscribe(Arc::new(recorder)),
+                    Err(e) => warn!("Failed to start recording history: {}", e),
+                }
+                Some(history)
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to open history; statistics won't be recorded: {}",
+                    e
+                );
+                None
             }
         };
+
         MinistoState {
-            xmrig: Arc::new(XmrigState::new()),
+            xmrig: Arc::new(XmrigState::new(data_dir.join("xmrig"))),
+            p2pool: Arc::new(P2poolState::new(data_dir.join("p2pool"))),
             monerod: Arc::new(MonerodState::new()),
             config: Arc::new(Mutex::new(config)),
             config_path,
+            config_error: StdMutex::new(config_error),
+            ports: StdMutex::new(Ports::default()),
+            startup: Startup::default(),
+            schedule: Schedule::default(),
+            events,
+            history,
         }
     }
+
+    /// Replace a configuration file that couldn't be read with its last good backup.
+    pub async fn restore_config(&self) -> Result<()> {
+        let config = Config::restore_backup(&self.config_path)?;
+        *self.config.lock().await = config;
+        *self
+            .config_error
+            .lock()
+            .expect("config error lock poisoned") = None;
+        info!("Restored the last good configuration");
+        Ok(())
+    }
+
+    pub fn config_error(&self) -> Option<String> {
+        self.config_error
+            .lock()
+            .expect("config error lock poisoned")
+            .clone()
+    }
 }
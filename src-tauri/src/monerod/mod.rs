@@ -0,0 +1,374 @@
+pub mod rpc;
+pub mod sync;
+
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Error, Result};
+use log::{debug, info, warn};
+use serde::Serialize;
+use tauri::{api::process::Command, command, State};
+use tokio::{
+    net::TcpStream,
+    sync::Mutex,
+    time::{interval, timeout},
+};
+
+use crate::config::{
+    daemon::{Daemon, LocalDaemon, RemoteDaemon},
+    pool::{LocalPool, Pool},
+    port,
+};
+use crate::{
+    events::{Event, MinistoEvents},
+    ports::Ports,
+    supervisor::{Component, StopResult, Supervisor},
+    MinistoState,
+};
+use rpc::{Ban, BlockHeader, Connection, Info, MonerodRpc, RpcError, SyncInfo};
+use sync::SyncTracker;
+
+/// How long to wait for the local daemon to respond. It can be slow while busy synchronizing.
+const LOCAL_RPC_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long to wait for a remote daemon to respond before considering it unreachable.
+const REMOTE_DAEMON_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub async fn start_monerod(state: &MinistoState) -> Result<()> {
+    let config = state.config.lock().await;
+    // No need to continue if we're not configured to use a local pool.
+    if let Pool::Local(LocalPool {
+        daemon: Daemon::Local(daemon),
+        ..
+    }) = &config.profile().pool
+    {
+        daemon.validate()?;
+        let ports = *state.ports.lock().expect("ports lock poisoned");
+        let args = monerod_args(daemon, &ports);
+        state.monerod.connect(local_rpc(ports.monerod_rpc));
+
+        let events: Arc<dyn MinistoEvents> = state.events.clone();
+        let output_events = events.clone();
+        state
+            .monerod
+            .process
+            .supervise(
+                config.supervisor,
+                events.clone(),
+                move || Ok(Command::new_sidecar("monerod")?.args(&args).spawn()?),
+                move |line| {
+                    debug!("{}", line);
+                    output_events.publish(&Event::Output {
+                        component: Component::Monerod,
+                        line,
+                    });
+                },
+            )
+            .await?;
+
+        let monerod_state = state.monerod.clone();
+        let rpc = state.monerod.rpc();
+        tauri::async_runtime::spawn(async move {
+            let mut interval = interval(Duration::from_secs(8));
+            let mut sync_tracker = SyncTracker::default();
+            loop {
+                interval.tick().await;
+                if !monerod_state.process.is_active().await {
+                    break;
+                }
+                // Get monerod status.
+                let status = match rpc.get_info().await {
+                    Ok(info) => {
+                        let progress = sync_tracker.record(Instant::now(), &info);
+                        events.publish(&Event::SyncProgress(progress));
+                        monerod_status(&info)
+                    }
+                    Err(RpcError::Daemon(_)) => Status::Error,
+                    Err(e) => {
+                        warn!("No response from Monerod: {}", e);
+                        continue;
+                    }
+                };
+                // Save status.
+                *monerod_state.status.lock().await = status;
+                // Send status event.
+                events.publish(&Event::MonerodStatus(status));
+            }
+            *monerod_state.status.lock().await = Status::Stopped;
+            events.publish(&Event::MonerodStatus(Status::Stopped));
+        });
+    } else if let Pool::Local(LocalPool {
+        daemon: Daemon::Remote(RemoteDaemon { ip, rpc_port, .. }),
+        ..
+    }) = &config.profile().pool
+    {
+        info!(
+            "Using remote daemon at {}:{}; not starting monerod",
+            ip, rpc_port
+        );
+    } else {
+        info!("Mining on a remote pool; not starting monerod");
+    }
+    Ok(())
+}
+
+/// Arguments monerod is started with.
+fn monerod_args(daemon: &LocalDaemon, ports: &Ports) -> Vec<String> {
+    let mut args = vec![
+        "--non-interactive".to_string(), // Don't accept stdin commands.
+        "--data-dir".to_string(),
+        daemon.blockchain_dir.clone(),
+        "--log-level".to_string(),
+        daemon.monerod_verbosity.to_string(),
+        "--rpc-bind-port".to_string(),
+        ports.monerod_rpc.to_string(),
+        "--p2p-bind-port".to_string(),
+        ports.monerod_p2p.to_string(),
+        "--zmq-pub".to_string(),
+        format!("tcp://127.0.0.1:{}", ports.monerod_zmq),
+        "--db-sync-mode".to_string(),
+        daemon.db_sync_mode.to_string(),
+    ];
+    if daemon.prune {
+        args.push("--prune-blockchain".to_string());
+        if daemon.sync_pruned_blocks {
+            args.push("--sync-pruned-blocks".to_string());
+        }
+    }
+    if daemon.ipv6 {
+        // IPv4 is still used as usual.
+        args.push("--p2p-use-ipv6".to_string());
+    }
+    if !daemon.dns_checkpoints {
+        args.push("--disable-dns-checkpoints".to_string());
+    }
+
+    let options = [
+        ("--out-peers", daemon.out_peers),
+        ("--in-peers", daemon.in_peers),
+        ("--limit-rate-up", daemon.limit_rate_up),
+        ("--limit-rate-down", daemon.limit_rate_down),
+    ];
+    for (flag, value) in options {
+        if let Some(value) = value {
+            args.extend([flag.to_string(), value.to_string()]);
+        }
+    }
+    if let Some(ban_list) = &daemon.ban_list {
+        args.extend(["--ban-list".to_string(), ban_list.clone()]);
+    }
+    for node in &daemon.priority_nodes {
+        args.push(format!("--add-priority-node={}", node));
+    }
+    for node in &daemon.exclusive_nodes {
+        args.push(format!("--add-exclusive-node={}", node));
+    }
+    args.extend(daemon.extra_args.iter().cloned());
+    args
+}
+
+/// Stop monerod using the `stop_daemon` RPC, so that the database is closed cleanly.
+pub async fn stop_monerod(state: &MonerodState) -> StopResult {
+    let rpc = state.rpc();
+    let request_exit = async { Ok(rpc.stop_daemon().await?) };
+    state
+        .process
+        .stop(request_exit, Duration::from_secs(60))
+        .await
+}
+
+/// Check that a remote daemon's RPC and ZMQ ports are reachable, and that we can log in. Returns a
+/// client for its RPC.
+pub async fn check_remote_daemon(daemon: &RemoteDaemon) -> Result<MonerodRpc> {
+    let rpc = MonerodRpc::new(
+        &format!("http://{}:{}", daemon.ip, daemon.rpc_port),
+        daemon.rpc_login.as_deref(),
+        REMOTE_DAEMON_TIMEOUT,
+    );
+    let version = rpc.get_version().await.map_err(|e| {
+        Error::msg(format!(
+            "Remote daemon RPC at {} is unusable: {}",
+            rpc.url(),
+            e
+        ))
+    })?;
+    info!(
+        "Remote daemon at {} has RPC version {}.{}",
+        rpc.url(),
+        version.version >> 16,
+        version.version & 0xffff
+    );
+
+    let zmq_address = format!("{}:{}", daemon.ip, daemon.zmq_port);
+    match timeout(REMOTE_DAEMON_TIMEOUT, TcpStream::connect(&zmq_address)).await {
+        Ok(Ok(_)) => Ok(rpc),
+        Ok(Err(e)) => Err(Error::msg(format!(
+            "Remote daemon ZMQ at {} is unreachable: {}",
+            zmq_address, e
+        ))),
+        Err(_) => Err(Error::msg(format!(
+            "Remote daemon ZMQ at {} timed out",
+            zmq_address
+        ))),
+    }
+}
+
+fn monerod_status(info: &Info) -> Status {
+    if info.busy_syncing {
+        Status::Synchronizing
+    } else if info.offline {
+        Status::Offline
+    } else if info.synchronized {
+        Status::Running
+    } else {
+        Status::Starting
+    }
+}
+
+#[command(async)]
+pub async fn monerod_sync_info(state: State<'_, MinistoState>) -> Result<SyncInfo, String> {
+    state
+        .monerod
+        .rpc()
+        .sync_info()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command(async)]
+pub async fn monerod_last_block_header(
+    state: State<'_, MinistoState>,
+) -> Result<BlockHeader, String> {
+    state
+        .monerod
+        .rpc()
+        .get_last_block_header()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[command(async)]
+pub async fn monerod_connections(
+    state: State<'_, MinistoState>,
+) -> Result<Vec<Connection>, String> {
+    state
+        .monerod
+        .rpc()
+        .get_connections()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Ban or unban misbehaving peers.
+#[command(async)]
+pub async fn monerod_set_bans(
+    state: State<'_, MinistoState>,
+    bans: Vec<Ban>,
+) -> Result<(), String> {
+    state
+        .monerod
+        .rpc()
+        .set_bans(&bans)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub enum Status {
+    Stopped,
+    Starting,
+    Offline,
+    Synchronizing,
+    Running,
+    Error,
+}
+
+#[derive(Debug)]
+pub struct MonerodState {
+    rpc: RwLock<Arc<MonerodRpc>>,
+    pub process: Arc<Supervisor>,
+    status: Mutex<Status>,
+}
+
+impl MonerodState {
+    pub fn new() -> MonerodState {
+        MonerodState {
+            rpc: RwLock::new(Arc::new(local_rpc(port::MONEROD_RPC))),
+            process: Arc::new(Supervisor::new(Component::Monerod)),
+            status: Mutex::new(Status::Stopped),
+        }
+    }
+
+    /// Client for the RPC of the daemon P2Pool was last started with, whether it's ours or remote.
+    pub fn rpc(&self) -> Arc<MonerodRpc> {
+        self.rpc.read().expect("monerod RPC lock poisoned").clone()
+    }
+
+    /// Point the RPC client at another daemon.
+    pub fn connect(&self, rpc: MonerodRpc) {
+        *self.rpc.write().expect("monerod RPC lock poisoned") = Arc::new(rpc);
+    }
+}
+
+fn local_rpc(rpc_port: u16) -> MonerodRpc {
+    MonerodRpc::new(
+        &format!("http://127.0.0.1:{}", rpc_port),
+        None,
+        LOCAL_RPC_TIMEOUT,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_args() {
+        let daemon = LocalDaemon {
+            blockchain_dir: "/blockchain".to_string(),
+            ..Default::default()
+        };
+        let args = monerod_args(&daemon, &Ports::default());
+        for expected in [
+            "--prune-blockchain",
+            "--sync-pruned-blocks",
+            "--p2p-use-ipv6",
+            "--disable-dns-checkpoints",
+            "safe:sync:250000000bytes",
+            "tcp://127.0.0.1:18083",
+            "/blockchain",
+        ] {
+            assert!(args.iter().any(|arg| arg == expected), "{:?}", args);
+        }
+        assert!(!args.iter().any(|arg| arg == "--out-peers"));
+    }
+
+    #[test]
+    fn optional_args() {
+        let daemon = LocalDaemon {
+            prune: false,
+            ipv6: false,
+            dns_checkpoints: true,
+            out_peers: Some(16),
+            limit_rate_down: Some(2048),
+            priority_nodes: vec!["node.example.com:18080".to_string()],
+            extra_args: vec!["--max-concurrency".to_string(), "2".to_string()],
+            ..Default::default()
+        };
+        let args = monerod_args(&daemon, &Ports::default());
+        for unexpected in [
+            "--prune-blockchain",
+            "--sync-pruned-blocks",
+            "--p2p-use-ipv6",
+            "--disable-dns-checkpoints",
+        ] {
+            assert!(!args.iter().any(|arg| arg == unexpected), "{:?}", args);
+        }
+        let joined = args.join(" ");
+        assert!(joined.contains("--out-peers 16"), "{}", joined);
+        assert!(joined.contains("--limit-rate-down 2048"), "{}", joined);
+        assert!(joined.contains("--add-priority-node=node.example.com:18080"));
+        assert!(joined.ends_with("--max-concurrency 2"), "{}", joined);
+    }
+}
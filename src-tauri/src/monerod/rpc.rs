@@ -0,0 +1,776 @@
+use std::{error, fmt, time::Duration};
+
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::{
+    header::{AUTHORIZATION, WWW_AUTHENTICATE},
+    Method, Response, StatusCode,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+/// Client for monerod's RPC interface.
+#[derive(Debug)]
+pub struct MonerodRpc {
+    client: reqwest::Client,
+    url: String,
+    login: Option<(String, String)>,
+    /// The last digest challenge the daemon sent us, reused until it goes stale.
+    challenge: Mutex<Option<Challenge>>,
+}
+
+impl MonerodRpc {
+    /// Create a client for the daemon at `url`, e.g. `http://127.0.0.1:18081`. `login` is in
+    /// monerod's `--rpc-login` format, `username:password`.
+    pub fn new(url: &str, login: Option<&str>, timeout: Duration) -> MonerodRpc {
+        let login = login.map(|login| match login.split_once(':') {
+            Some((user, password)) => (user.to_string(), password.to_string()),
+            None => (login.to_string(), String::new()),
+        });
+        MonerodRpc {
+            client: reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .expect("failed to build monerod RPC client"),
+            url: url.trim_end_matches('/').to_string(),
+            login,
+            challenge: Mutex::new(None),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub async fn get_info(&self) -> Result<Info, RpcError> {
+        self.json_rpc("get_info", json!({})).await
+    }
+
+    pub async fn sync_info(&self) -> Result<SyncInfo, RpcError> {
+        self.json_rpc("sync_info", json!({})).await
+    }
+
+    pub async fn get_last_block_header(&self) -> Result<BlockHeader, RpcError> {
+        let response: BlockHeaderResponse =
+            self.json_rpc("get_last_block_header", json!({})).await?;
+        Ok(response.block_header)
+    }
+
+    pub async fn get_connections(&self) -> Result<Vec<Connection>, RpcError> {
+        let response: ConnectionsResponse = self.json_rpc("get_connections", json!({})).await?;
+        Ok(response.connections)
+    }
+
+    /// Ban or unban peers.
+    pub async fn set_bans(&self, bans: &[Ban]) -> Result<(), RpcError> {
+        let _: Value = self.json_rpc("set_bans", json!({ "bans": bans })).await?;
+        Ok(())
+    }
+
+    pub async fn get_version(&self) -> Result<Version, RpcError> {
+        self.json_rpc("get_version", json!({})).await
+    }
+
+    /// Ask the daemon to save the blockchain and exit.
+    pub async fn stop_daemon(&self) -> Result<(), RpcError> {
+        let response = self.send(Method::POST, "/stop_daemon", &json!({})).await?;
+        let _: Value = parse(response).await?;
+        Ok(())
+    }
+
+    async fn json_rpc<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, RpcError> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": "0",
+            "method": method,
+            "params": params,
+        });
+        let response = self.send(Method::POST, "/json_rpc", &body).await?;
+        let response: JsonRpcResponse = parse(response).await?;
+        if let Some(e) = response.error {
+            return Err(RpcError::Rpc {
+                code: e.code,
+                message: e.message,
+            });
+        }
+        let result = response.result.unwrap_or(Value::Null);
+        check_status(&result)?;
+        serde_json::from_value(result).map_err(RpcError::Parse)
+    }
+
+    /// Send a request, answering the daemon's digest challenge if it asks for one.
+    async fn send(&self, method: Method, path: &str, body: &Value) -> Result<Response, RpcError> {
+        let response = self.try_send(method.clone(), path, body).await?;
+        if response.status() != StatusCode::UNAUTHORIZED || self.login.is_none() {
+            return Ok(response);
+        }
+        let challenge = response
+            .headers()
+            .get_all(WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(Challenge::parse);
+        match challenge {
+            Some(challenge) => {
+                *self.challenge.lock().await = Some(challenge);
+                self.try_send(method, path, body).await
+            }
+            None => Ok(response),
+        }
+    }
+
+    async fn try_send(
+        &self,
+        method: Method,
+        path: &str,
+        body: &Value,
+    ) -> Result<Response, RpcError> {
+        let mut request = self
+            .client
+            .request(method.clone(), format!("{}{}", self.url, path))
+            .json(body);
+        if let Some((user, password)) = &self.login {
+            if let Some(challenge) = self.challenge.lock().await.as_mut() {
+                challenge.nonce_count += 1;
+                let cnonce: String = rand::thread_rng()
+                    .sample_iter(&Alphanumeric)
+                    .take(16)
+                    .map(char::from)
+                    .collect();
+                let authorization =
+                    challenge.authorization(user, password, method.as_str(), path, &cnonce);
+                request = request.header(AUTHORIZATION, authorization);
+            }
+        }
+        request.send().await.map_err(RpcError::Http)
+    }
+}
+
+async fn parse<T: DeserializeOwned>(response: Response) -> Result<T, RpcError> {
+    match response.status() {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => return Err(RpcError::Unauthorized),
+        status if !status.is_success() => return Err(RpcError::Status(status)),
+        _ => {}
+    }
+    let body = response.text().await.map_err(RpcError::Http)?;
+    let value: Value = serde_json::from_str(&body).map_err(RpcError::Parse)?;
+    check_status(&value)?;
+    serde_json::from_value(value).map_err(RpcError::Parse)
+}
+
+/// monerod reports some failures in a `status` field rather than as an error.
+fn check_status(result: &Value) -> Result<(), RpcError> {
+    match result.get("status").and_then(Value::as_str) {
+        Some(status) if status != "OK" => Err(RpcError::Daemon(status.to_string())),
+        _ => Ok(()),
+    }
+}
+
+/// An HTTP digest authentication challenge (RFC 7616), as sent by monerod when `--rpc-login` is
+/// set.
+#[derive(Debug, Clone, PartialEq)]
+struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    qop_auth: bool,
+    nonce_count: u32,
+}
+
+impl Challenge {
+    /// Parse a `WWW-Authenticate` header, returning `None` unless it is an MD5 digest challenge.
+    fn parse(header: &str) -> Option<Challenge> {
+        let (scheme, params) = header.trim().split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("digest") {
+            return None;
+        }
+
+        let mut challenge = Challenge {
+            realm: String::new(),
+            nonce: String::new(),
+            opaque: None,
+            qop_auth: false,
+            nonce_count: 0,
+        };
+        let mut nonce = None;
+        for (key, value) in split_params(params) {
+            match key.to_ascii_lowercase().as_str() {
+                "realm" => challenge.realm = value,
+                "nonce" => nonce = Some(value),
+                "opaque" => challenge.opaque = Some(value),
+                "qop" => challenge.qop_auth = value.split(',').any(|qop| qop.trim() == "auth"),
+                "algorithm" if !value.eq_ignore_ascii_case("md5") => return None,
+                _ => {}
+            }
+        }
+        challenge.nonce = nonce?;
+        Some(challenge)
+    }
+
+    /// Value of the `Authorization` header answering this challenge.
+    fn authorization(
+        &self,
+        user: &str,
+        password: &str,
+        method: &str,
+        uri: &str,
+        cnonce: &str,
+    ) -> String {
+        let ha1 = md5_hex(&format!("{}:{}:{}", user, self.realm, password));
+        let ha2 = md5_hex(&format!("{}:{}", method, uri));
+        let nc = format!("{:08x}", self.nonce_count);
+
+        let mut header = format!(
+            r#"Digest username="{}", realm="{}", nonce="{}", uri="{}", algorithm=MD5"#,
+            user, self.realm, self.nonce, uri
+        );
+        if self.qop_auth {
+            let response = md5_hex(&format!(
+                "{}:{}:{}:{}:auth:{}",
+                ha1, self.nonce, nc, cnonce, ha2
+            ));
+            header += &format!(
+                r#", qop=auth, nc={}, cnonce="{}", response="{}""#,
+                nc, cnonce, response
+            );
+        } else {
+            let response = md5_hex(&format!("{}:{}:{}", ha1, self.nonce, ha2));
+            header += &format!(r#", response="{}""#, response);
+        }
+        if let Some(opaque) = &self.opaque {
+            header += &format!(r#", opaque="{}""#, opaque);
+        }
+        header
+    }
+}
+
+/// Split `key=value, key="quoted, value"` pairs.
+fn split_params(params: &str) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    let mut rest = params.trim();
+    while let Some((key, after)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_string();
+        let after = after.trim_start();
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            },
+            None => match after.find(',') {
+                Some(end) => (&after[..end], &after[end..]),
+                None => (after, ""),
+            },
+        };
+        pairs.push((key, value.trim().to_string()));
+        rest = after.trim_start().trim_start_matches(',');
+    }
+    pairs
+}
+
+fn md5_hex(data: &str) -> String {
+    format!("{:x}", md5::compute(data))
+}
+
+#[derive(Debug)]
+pub enum RpcError {
+    /// monerod could not be reached.
+    Http(reqwest::Error),
+    /// monerod rejected our RPC login, or requires one we don't have.
+    Unauthorized,
+    /// monerod responded with an unexpected HTTP status.
+    Status(StatusCode),
+    /// monerod's response could not be parsed.
+    Parse(serde_json::Error),
+    /// monerod responded to a json_rpc call with an error.
+    Rpc { code: i64, message: String },
+    /// monerod responded with a status other than "OK", e.g. "BUSY".
+    Daemon(String),
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RpcError::Http(e) => write!(f, "failed to reach monerod: {}", e),
+            RpcError::Unauthorized => write!(f, "monerod rejected the RPC login"),
+            RpcError::Status(status) => write!(f, "monerod responded with {}", status),
+            RpcError::Parse(e) => write!(f, "failed to parse monerod response: {}", e),
+            RpcError::Rpc { code, message } => {
+                write!(f, "monerod responded with error {}: {}", code, message)
+            }
+            RpcError::Daemon(status) => write!(f, "monerod responded with status {}", status),
+        }
+    }
+}
+
+impl error::Error for RpcError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            RpcError::Http(e) => Some(e),
+            RpcError::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonRpcResponse {
+    result: Option<Value>,
+    error: Option<JsonRpcError>,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcError {
+    code: i64,
+    message: String,
+}
+
+/// Result of monerod's `get_info` json rpc method.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Info {
+    pub adjusted_time: u64,
+    pub alt_blocks_count: u64,
+    pub block_size_limit: u64,
+    pub block_size_median: u64,
+    pub block_weight_limit: u64,
+    pub block_weight_median: u64,
+    pub bootstrap_daemon_address: String,
+    pub busy_syncing: bool,
+    pub credits: u64,
+    pub cumulative_difficulty: u64,
+    pub cumulative_difficulty_top64: u64,
+    pub database_size: u64,
+    pub difficulty: u64,
+    pub difficulty_top64: u64,
+    pub free_space: u64,
+    pub grey_peerlist_size: u64,
+    pub height: u64,
+    pub height_without_bootstrap: u64,
+    pub incoming_connections_count: u64,
+    pub mainnet: bool,
+    pub nettype: String,
+    pub offline: bool,
+    pub outgoing_connections_count: u64,
+    pub rpc_connections_count: u64,
+    pub stagenet: bool,
+    pub start_time: u64,
+    pub status: String,
+    pub synchronized: bool,
+    pub target: u64,
+    pub target_height: u64,
+    pub testnet: bool,
+    pub top_block_hash: String,
+    pub tx_count: u64,
+    pub tx_pool_size: u64,
+    pub untrusted: bool,
+    pub update_available: bool,
+    pub version: String,
+    pub was_bootstrap_ever_used: bool,
+    pub white_peerlist_size: u64,
+    pub wide_cumulative_difficulty: String,
+    pub wide_difficulty: String,
+}
+
+/// Result of monerod's `sync_info` json rpc method.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct SyncInfo {
+    pub height: u64,
+    pub target_height: u64,
+    pub next_needed_pruning_seed: u32,
+    pub overview: String,
+    pub peers: Vec<SyncPeer>,
+    pub spans: Vec<Span>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct SyncPeer {
+    pub info: Connection,
+}
+
+/// A range of blocks being downloaded from a peer.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Span {
+    pub connection_id: String,
+    pub nblocks: u64,
+    pub rate: u64,
+    pub remote_address: String,
+    pub size: u64,
+    pub speed: u64,
+    pub start_block_height: u64,
+}
+
+/// A peer, as described by monerod's `get_connections` and `sync_info` json rpc methods.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Connection {
+    pub address: String,
+    pub avg_download: u64,
+    pub avg_upload: u64,
+    pub connection_id: String,
+    pub current_download: u64,
+    pub current_upload: u64,
+    pub height: u64,
+    pub host: String,
+    pub incoming: bool,
+    pub ip: String,
+    pub live_time: u64,
+    pub local_ip: bool,
+    pub localhost: bool,
+    pub peer_id: String,
+    pub port: String,
+    pub pruning_seed: u32,
+    pub recv_count: u64,
+    pub recv_idle_time: u64,
+    pub send_count: u64,
+    pub send_idle_time: u64,
+    pub state: String,
+    pub support_flags: u32,
+}
+
+#[derive(Deserialize)]
+struct ConnectionsResponse {
+    #[serde(default)]
+    connections: Vec<Connection>,
+}
+
+/// A block header, as returned by monerod's `get_last_block_header` json rpc method.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct BlockHeader {
+    pub block_size: u64,
+    pub block_weight: u64,
+    pub cumulative_difficulty: u64,
+    pub depth: u64,
+    pub difficulty: u64,
+    pub hash: String,
+    pub height: u64,
+    pub major_version: u32,
+    pub minor_version: u32,
+    pub nonce: u64,
+    pub num_txes: u64,
+    pub orphan_status: bool,
+    pub prev_hash: String,
+    pub reward: u64,
+    pub timestamp: u64,
+}
+
+#[derive(Deserialize)]
+struct BlockHeaderResponse {
+    block_header: BlockHeader,
+}
+
+/// An entry for monerod's `set_bans` json rpc method.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Ban {
+    /// Host to ban, as an IP address.
+    pub host: String,
+    /// `true` to ban the host, `false` to lift its ban.
+    pub ban: bool,
+    /// How long to ban the host for.
+    pub seconds: u64,
+}
+
+/// Result of monerod's `get_version` json rpc method.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Version {
+    /// RPC version, with the major version in the upper 16 bits and the minor in the lower.
+    pub version: u32,
+    pub release: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        task::JoinHandle,
+        time::sleep,
+    };
+
+    /// Serve each of `responses` to one connection, in order, returning the requests received.
+    async fn mock_server(responses: Vec<String>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                requests.push(read_request(&mut stream).await);
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.shutdown().await.unwrap();
+            }
+            requests
+        });
+        (url, server)
+    }
+
+    async fn read_request(stream: &mut tokio::net::TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some(end) = text.find("\r\n\r\n") {
+                let content_length = text[..end]
+                    .lines()
+                    .filter_map(|line| line.split_once(':'))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                    .map(|(_, value)| value.trim().parse::<usize>().unwrap())
+                    .unwrap_or(0);
+                if request.len() >= end + 4 + content_length || n == 0 {
+                    return text.into_owned();
+                }
+            }
+        }
+    }
+
+    fn response(status: &str, headers: &[&str], body: &str) -> String {
+        let mut response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+            status,
+            body.len()
+        );
+        for header in headers {
+            response += &format!("{}\r\n", header);
+        }
+        response + "\r\n" + body
+    }
+
+    fn json_rpc_result(result: Value) -> String {
+        let body = json!({"id": "0", "jsonrpc": "2.0", "result": result});
+        response("200 OK", &[], &body.to_string())
+    }
+
+    fn client(url: &str, login: Option<&str>) -> MonerodRpc {
+        MonerodRpc::new(url, login, Duration::from_secs(5))
+    }
+
+    #[tokio::test]
+    async fn get_info() {
+        let (url, server) = mock_server(vec![json_rpc_result(json!({
+            "height": 2500000,
+            "target_height": 2700000,
+            "busy_syncing": true,
+            "status": "OK",
+        }))])
+        .await;
+
+        let info = client(&url, None).get_info().await.unwrap();
+        assert_eq!(info.height, 2500000);
+        assert_eq!(info.target_height, 2700000);
+        assert!(info.busy_syncing);
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("POST /json_rpc "));
+        assert!(requests[0].contains(r#""method":"get_info""#));
+    }
+
+    #[tokio::test]
+    async fn sync_info() {
+        let (url, _server) = mock_server(vec![json_rpc_result(json!({
+            "height": 100,
+            "target_height": 200,
+            "peers": [{"info": {"address": "1.2.3.4:18080", "height": 200, "incoming": false}}],
+            "spans": [{"nblocks": 20, "start_block_height": 101}],
+            "status": "OK",
+        }))])
+        .await;
+
+        let sync_info = client(&url, None).sync_info().await.unwrap();
+        assert_eq!(sync_info.target_height, 200);
+        assert_eq!(sync_info.peers[0].info.address, "1.2.3.4:18080");
+        assert_eq!(sync_info.spans[0].start_block_height, 101);
+    }
+
+    #[tokio::test]
+    async fn get_last_block_header() {
+        let (url, _server) = mock_server(vec![json_rpc_result(json!({
+            "block_header": {"height": 2700000, "hash": "abc", "reward": 600000000000_u64},
+            "status": "OK",
+        }))])
+        .await;
+
+        let header = client(&url, None).get_last_block_header().await.unwrap();
+        assert_eq!(header.height, 2700000);
+        assert_eq!(header.hash, "abc");
+        assert_eq!(header.reward, 600000000000);
+    }
+
+    #[tokio::test]
+    async fn get_connections() {
+        let (url, _server) = mock_server(vec![json_rpc_result(json!({
+            "connections": [
+                {"address": "1.2.3.4:18080", "incoming": true},
+                {"address": "5.6.7.8:18080", "incoming": false},
+            ],
+            "status": "OK",
+        }))])
+        .await;
+
+        let connections = client(&url, None).get_connections().await.unwrap();
+        assert_eq!(connections.len(), 2);
+        assert!(connections[0].incoming);
+        assert_eq!(connections[1].address, "5.6.7.8:18080");
+    }
+
+    #[tokio::test]
+    async fn set_bans() {
+        let (url, server) = mock_server(vec![json_rpc_result(json!({"status": "OK"}))]).await;
+
+        let bans = [Ban {
+            host: "1.2.3.4".to_string(),
+            ban: true,
+            seconds: 3600,
+        }];
+        client(&url, None).set_bans(&bans).await.unwrap();
+
+        let requests = server.await.unwrap();
+        let body = &requests[0][requests[0].find("\r\n\r\n").unwrap() + 4..];
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            body["params"],
+            json!({"bans": [{"host": "1.2.3.4", "ban": true, "seconds": 3600}]})
+        );
+    }
+
+    #[tokio::test]
+    async fn get_version() {
+        let (url, _server) = mock_server(vec![json_rpc_result(json!({
+            "version": 196613,
+            "release": true,
+            "status": "OK",
+        }))])
+        .await;
+
+        let version = client(&url, None).get_version().await.unwrap();
+        assert_eq!(version.version >> 16, 3);
+        assert!(version.release);
+    }
+
+    #[tokio::test]
+    async fn stop_daemon() {
+        let (url, server) = mock_server(vec![response("200 OK", &[], r#"{"status": "OK"}"#)]).await;
+
+        client(&url, None).stop_daemon().await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("POST /stop_daemon "));
+    }
+
+    #[tokio::test]
+    async fn json_rpc_error() {
+        let body = json!({
+            "id": "0",
+            "jsonrpc": "2.0",
+            "error": {"code": -32601, "message": "Method not found"},
+        });
+        let (url, _server) = mock_server(vec![response("200 OK", &[], &body.to_string())]).await;
+
+        match client(&url, None).get_info().await {
+            Err(RpcError::Rpc { code, message }) => {
+                assert_eq!(code, -32601);
+                assert_eq!(message, "Method not found");
+            }
+            other => panic!("expected an RPC error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn daemon_status() {
+        let (url, _server) = mock_server(vec![json_rpc_result(json!({"status": "BUSY"}))]).await;
+
+        match client(&url, None).get_info().await {
+            Err(RpcError::Daemon(status)) => assert_eq!(status, "BUSY"),
+            other => panic!("expected a daemon status error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn digest_authentication() {
+        let challenge = r#"WWW-Authenticate: Digest qop="auth", algorithm=MD5, realm="monero-rpc", nonce="abc123", stale=false"#;
+        let (url, server) = mock_server(vec![
+            response("401 Unauthorized", &[challenge], ""),
+            json_rpc_result(json!({"height": 1, "status": "OK"})),
+            json_rpc_result(json!({"height": 2, "status": "OK"})),
+        ])
+        .await;
+
+        let rpc = client(&url, Some("user:pass"));
+        assert_eq!(rpc.get_info().await.unwrap().height, 1);
+        // The challenge is reused rather than waiting for the daemon to send it again.
+        assert_eq!(rpc.get_info().await.unwrap().height, 2);
+
+        let requests = server.await.unwrap();
+        assert!(!requests[0].to_lowercase().contains("authorization:"));
+        assert!(requests[1].contains(r#"username="user""#));
+        assert!(requests[1].contains("nc=00000001"));
+        assert!(requests[2].contains("nc=00000002"));
+    }
+
+    #[tokio::test]
+    async fn unauthorized_without_login() {
+        let challenge = r#"WWW-Authenticate: Digest qop="auth", realm="monero-rpc", nonce="abc""#;
+        let (url, _server) =
+            mock_server(vec![response("401 Unauthorized", &[challenge], "")]).await;
+
+        assert!(matches!(
+            client(&url, None).get_info().await,
+            Err(RpcError::Unauthorized)
+        ));
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let _server = tokio::spawn(async move {
+            let (_stream, _) = listener.accept().await.unwrap();
+            sleep(Duration::from_secs(10)).await;
+        });
+
+        let rpc = MonerodRpc::new(&url, None, Duration::from_millis(100));
+        match rpc.get_info().await {
+            Err(RpcError::Http(e)) => assert!(e.is_timeout()),
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn digest_response() {
+        // Example from RFC 2617, section 3.5.
+        let mut challenge = Challenge::parse(
+            r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#,
+        )
+        .unwrap();
+        challenge.nonce_count = 1;
+        let authorization = challenge.authorization(
+            "Mufasa",
+            "Circle Of Life",
+            "GET",
+            "/dir/index.html",
+            "0a4f113b",
+        );
+        assert!(authorization.contains(r#"response="6629fae49393a05397450978507c4ef1""#));
+        assert!(authorization.contains(r#"opaque="5ccc069c403ebaf9f0171e9517f40e41""#));
+    }
+
+    #[test]
+    fn unsupported_challenges() {
+        assert_eq!(Challenge::parse(r#"Basic realm="monero-rpc""#), None);
+        assert_eq!(
+            Challenge::parse(r#"Digest realm="r", nonce="n", algorithm=SHA-256"#),
+            None
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use super::rpc::Info;
+
+/// How far back to look when estimating the sync rate. Long enough to smooth over slow batches,
+/// short enough to follow the rate as it changes with block sizes.
+const RATE_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// How far the local blockchain is from the network's.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncProgress {
+    pub height: u64,
+    pub target_height: u64,
+    pub percent: f64,
+    /// Blocks synchronized per second over the last few minutes, once there's enough data.
+    pub blocks_per_second: Option<f64>,
+    /// Estimated seconds until synchronized, if blocks are currently being synchronized.
+    pub eta_secs: Option<u64>,
+    pub database_size: u64,
+    pub free_space: u64,
+}
+
+/// Follows the daemon's height over time, to estimate how fast it's synchronizing.
+#[derive(Debug, Default)]
+pub struct SyncTracker {
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl SyncTracker {
+    /// Record the daemon's height as of `now`, and report its progress.
+    pub fn record(&mut self, now: Instant, info: &Info) -> SyncProgress {
+        // `target_height` is 0 once the daemon no longer knows of a taller chain.
+        let target_height = info.target_height.max(info.height);
+
+        // Heights only go down if the daemon was restarted or reorganized, which invalidates the
+        // rate measured so far.
+        if matches!(self.samples.back(), Some(&(_, height)) if height > info.height) {
+            self.samples.clear();
+        }
+        self.samples.push_back((now, info.height));
+        while matches!(self.samples.front(), Some(&(at, _)) if now.duration_since(at) > RATE_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+
+        let blocks_per_second = match (self.samples.front(), self.samples.back()) {
+            (Some(&(first_at, first_height)), Some(&(last_at, last_height)))
+                if last_at > first_at =>
+            {
+                let elapsed = last_at.duration_since(first_at).as_secs_f64();
+                Some((last_height - first_height) as f64 / elapsed)
+            }
+            _ => None,
+        };
+        let remaining = target_height - info.height;
+        let eta_secs = match blocks_per_second {
+            Some(rate) if rate > 0.0 && remaining > 0 => Some((remaining as f64 / rate) as u64),
+            _ => None,
+        };
+        let percent = if target_height == 0 {
+            0.0
+        } else {
+            info.height as f64 * 100.0 / target_height as f64
+        };
+
+        SyncProgress {
+            height: info.height,
+            target_height,
+            percent,
+            blocks_per_second,
+            eta_secs,
+            database_size: info.database_size,
+            free_space: info.free_space,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(height: u64, target_height: u64) -> Info {
+        Info {
+            height,
+            target_height,
+            database_size: 1000,
+            free_space: 2000,
+            ..Info::default()
+        }
+    }
+
+    #[test]
+    fn estimates_rate_and_eta() {
+        let start = Instant::now();
+        let mut tracker = SyncTracker::default();
+
+        let first = tracker.record(start, &info(1000, 2000));
+        assert_eq!(first.blocks_per_second, None);
+        assert_eq!(first.eta_secs, None);
+        assert_eq!(first.percent, 50.0);
+        assert_eq!(first.free_space, 2000);
+
+        let second = tracker.record(start + Duration::from_secs(10), &info(1100, 2000));
+        assert_eq!(second.blocks_per_second, Some(10.0));
+        assert_eq!(second.eta_secs, Some(90));
+        assert_eq!(second.percent, 55.0);
+    }
+
+    #[test]
+    fn forgets_old_samples() {
+        let start = Instant::now();
+        let mut tracker = SyncTracker::default();
+
+        tracker.record(start, &info(0, 10000));
+        tracker.record(start + Duration::from_secs(60), &info(6000, 10000));
+        let progress = tracker.record(
+            start + RATE_WINDOW + Duration::from_secs(60),
+            &info(6300, 10000),
+        );
+        // Only the last two samples are within the window.
+        assert_eq!(
+            progress.blocks_per_second,
+            Some(300.0 / RATE_WINDOW.as_secs_f64())
+        );
+    }
+
+    #[test]
+    fn synchronized() {
+        let start = Instant::now();
+        let mut tracker = SyncTracker::default();
+
+        tracker.record(start, &info(2000, 2000));
+        let progress = tracker.record(start + Duration::from_secs(120), &info(2001, 0));
+        assert_eq!(progress.target_height, 2001);
+        assert_eq!(progress.percent, 100.0);
+        assert_eq!(progress.eta_secs, None);
+    }
+
+    #[test]
+    fn restarts_after_height_drops() {
+        let start = Instant::now();
+        let mut tracker = SyncTracker::default();
+
+        tracker.record(start, &info(5000, 10000));
+        let progress = tracker.record(start + Duration::from_secs(10), &info(10, 10000));
+        assert_eq!(progress.blocks_per_second, None);
+    }
+}
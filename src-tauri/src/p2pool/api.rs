@@ -0,0 +1,196 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Reader for the statistics P2Pool writes to its `--data-api` directory.
+#[derive(Debug, Clone)]
+pub struct DataApi {
+    dir: PathBuf,
+}
+
+impl DataApi {
+    pub fn new(dir: PathBuf) -> DataApi {
+        DataApi { dir }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Statistics of our own miners, from `local/stratum`. Requires `--local-api`.
+    pub fn local_stratum(&self) -> Result<LocalStratum> {
+        self.read("local/stratum")
+    }
+
+    /// Statistics of the P2Pool sidechain, from `pool/stats`.
+    pub fn pool_stats(&self) -> Result<PoolStats> {
+        self.read("pool/stats")
+    }
+
+    /// Statistics of the Monero network, from `network/stats`.
+    pub fn network_stats(&self) -> Result<NetworkStats> {
+        self.read("network/stats")
+    }
+
+    fn read<T: DeserializeOwned>(&self, file: &str) -> Result<T> {
+        let contents = fs::read_to_string(self.dir.join(file))?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+/// Contents of P2Pool's `local/stratum` file.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct LocalStratum {
+    pub hashrate_15m: f64,
+    pub hashrate_1h: f64,
+    pub hashrate_24h: f64,
+    pub total_hashes: u64,
+    pub shares_found: u64,
+    pub shares_failed: u64,
+    /// Average effort of the shares found, in percent.
+    pub average_effort: f64,
+    /// Effort spent towards the next share, in percent.
+    pub current_effort: f64,
+    pub connections: u64,
+    pub incoming_connections: u64,
+    /// Our share of the reward for the next block P2Pool finds, in percent.
+    pub block_reward_share_percent: f64,
+    /// Only written by newer versions of P2Pool.
+    pub last_share_found_time: Option<u64>,
+}
+
+/// Contents of P2Pool's `pool/stats` file.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct PoolStats {
+    pub pool_statistics: PoolStatistics,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PoolStatistics {
+    pub hash_rate: f64,
+    pub miners: u64,
+    pub total_hashes: u64,
+    pub last_block_found_time: u64,
+    pub last_block_found: u64,
+    pub total_blocks_found: u64,
+    pub pplns_weight: u64,
+    pub pplns_window_size: u64,
+    pub sidechain_difficulty: u64,
+    pub sidechain_height: u64,
+}
+
+/// Contents of P2Pool's `network/stats` file.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct NetworkStats {
+    pub difficulty: u64,
+    pub hash: String,
+    pub height: u64,
+    /// Reward of the last block, in atomic units.
+    pub reward: u64,
+    pub timestamp: u64,
+}
+
+/// Our standing in P2Pool, as shown to the user.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Status {
+    pub hashrate: f64,
+    pub shares_found: u64,
+    pub shares_failed: u64,
+    pub average_effort: f64,
+    pub current_effort: f64,
+    /// Our payout when P2Pool next finds a block, in atomic units.
+    pub estimated_payout: u64,
+    /// When we last found a share, in seconds since the Unix epoch. Only reported by newer
+    /// versions of P2Pool.
+    pub last_share_time: Option<u64>,
+    pub sidechain_height: u64,
+    pub sidechain_difficulty: u64,
+    pub pool_hashrate: f64,
+    pub miners: u64,
+}
+
+impl Status {
+    pub fn new(local: &LocalStratum, pool: &PoolStats, network: &NetworkStats) -> Status {
+        Status {
+            hashrate: local.hashrate_15m,
+            shares_found: local.shares_found,
+            shares_failed: local.shares_failed,
+            average_effort: local.average_effort,
+            current_effort: local.current_effort,
+            estimated_payout: (network.reward as f64 * local.block_reward_share_percent / 100.0)
+                as u64,
+            last_share_time: local.last_share_found_time.filter(|&time| time > 0),
+            sidechain_height: pool.pool_statistics.sidechain_height,
+            sidechain_difficulty: pool.pool_statistics.sidechain_difficulty,
+            pool_hashrate: pool.pool_statistics.hash_rate,
+            miners: pool.pool_statistics.miners,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::tempdir;
+
+    #[test]
+    fn reads_data_api() {
+        let dir = tempdir().unwrap();
+        for sub_dir in ["local", "pool", "network"] {
+            fs::create_dir(dir.path().join(sub_dir)).unwrap();
+        }
+        fs::write(
+            dir.path().join("local/stratum"),
+            r#"{"hashrate_15m":5000,"hashrate_1h":4900,"hashrate_24h":4800,"total_hashes":123456789,
+            "shares_found":7,"shares_failed":1,"average_effort":95.5,"current_effort":40.1,
+            "connections":2,"incoming_connections":0,"block_reward_share_percent":0.5,
+            "workers":["127.0.0.1:50000,5000,123456789,0,x"]}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("pool/stats"),
+            r#"{"pool_list":["pplns"],"pool_statistics":{"hashRate":12000000,"miners":900,
+            "totalHashes":1,"lastBlockFoundTime":1,"lastBlockFound":2,"totalBlocksFound":3,
+            "pplnsWeight":4,"pplnsWindowSize":2160,"sidechainDifficulty":120000000,
+            "sidechainHeight":4000000}}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("network/stats"),
+            r#"{"difficulty":300000000000,"hash":"abc","height":2900000,"reward":600000000000,
+            "timestamp":1700000000}"#,
+        )
+        .unwrap();
+
+        let api = DataApi::new(dir.path().to_path_buf());
+        let status = Status::new(
+            &api.local_stratum().unwrap(),
+            &api.pool_stats().unwrap(),
+            &api.network_stats().unwrap(),
+        );
+        assert_eq!(status.shares_found, 7);
+        assert_eq!(status.current_effort, 40.1);
+        assert_eq!(status.estimated_payout, 3000000000);
+        assert_eq!(status.sidechain_height, 4000000);
+        assert_eq!(status.miners, 900);
+        assert_eq!(status.last_share_time, None);
+    }
+
+    #[test]
+    fn missing_files() {
+        let dir = tempdir().unwrap();
+        assert!(DataApi::new(dir.path().to_path_buf())
+            .local_stratum()
+            .is_err());
+    }
+}
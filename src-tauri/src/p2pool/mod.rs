@@ -0,0 +1,160 @@
+pub mod api;
+
+use std::{fs, path::PathBuf, sync::Arc, time::Duration};
+
+use anyhow::{Error, Result};
+use log::{debug, info};
+use tauri::api::process::Command;
+use tokio::time::interval;
+
+use self::api::{DataApi, Status};
+use crate::config::{
+    daemon::Daemon,
+    pool::{LocalPool, P2poolChain, Pool},
+};
+use crate::{
+    events::{Event, MinistoEvents},
+    monerod::check_remote_daemon,
+    supervisor::{Component, StopResult, Supervisor},
+    MinistoState,
+};
+
+pub async fn start_p2pool(state: &MinistoState) -> Result<()> {
+    // Don't hold the config lock while checking a remote daemon, which can take a while.
+    let (pool, supervisor) = {
+        let config = state.config.lock().await;
+        (config.profile().pool.clone(), config.supervisor)
+    };
+    // No need to continue if we're not configured to use a local pool.
+    if let Pool::Local(LocalPool {
+        monero_address,
+        chain,
+        p2pool_verbosity,
+        daemon,
+        ..
+    }) = &pool
+    {
+        let ports = *state.ports.lock().expect("ports lock poisoned");
+        let address = monero_address
+            .as_ref()
+            .ok_or_else(|| Error::msg("Monero address not configured"))?;
+        let (host, rpc_port, zmq_port, rpc_login) = match daemon {
+            Daemon::Local(_) => ("127.0.0.1", ports.monerod_rpc, ports.monerod_zmq, None),
+            Daemon::Remote(remote) => {
+                // Fail early rather than have P2Pool retry endlessly against a missing daemon.
+                let rpc = check_remote_daemon(remote).await?;
+                state.monerod.connect(rpc);
+                (
+                    remote.ip.as_str(),
+                    remote.rpc_port,
+                    remote.zmq_port,
+                    remote.rpc_login.as_deref(),
+                )
+            }
+        };
+        let rpc_port = rpc_port.to_string();
+        let zmq_port = zmq_port.to_string();
+        let verbosity_str = p2pool_verbosity.to_string();
+        // Listen on IPv6 and IPv4, as P2Pool does by default.
+        let stratum = format!("[::]:{port},0.0.0.0:{port}", port = ports.p2pool_stratum);
+        let mut args = vec![
+            "--host",
+            host,
+            "--rpc-port",
+            &rpc_port,
+            "--zmq-port",
+            &zmq_port,
+            "--light-mode",
+            "--loglevel",
+            &verbosity_str,
+            "--wallet",
+            address,
+            "--stratum",
+            &stratum,
+        ];
+        if let Some(login) = rpc_login {
+            args.extend(["--rpc-login", login]);
+        }
+        if let P2poolChain::Mini = chain {
+            args.push("--mini");
+        }
+        // Have P2Pool write its statistics where we can read them.
+        let data_api_dir = state.p2pool.data_api.dir().to_string_lossy().into_owned();
+        fs::create_dir_all(&data_api_dir)?;
+        args.extend(["--data-api", &data_api_dir, "--local-api"]);
+        let args: Vec<String> = args.into_iter().map(String::from).collect();
+
+        let events: Arc<dyn MinistoEvents> = state.events.clone();
+        let output_events = events.clone();
+        state
+            .p2pool
+            .process
+            .supervise(
+                supervisor,
+                events.clone(),
+                move || Ok(Command::new_sidecar("p2pool")?.args(&args).spawn()?),
+                move |line| {
+                    debug!("{}", line);
+                    output_events.publish(&Event::Output {
+                        component: Component::P2pool,
+                        line,
+                    });
+                },
+            )
+            .await?;
+
+        let p2pool_state = state.p2pool.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut interval = interval(Duration::from_secs(8));
+            loop {
+                interval.tick().await;
+                if !p2pool_state.process.is_active().await {
+                    break;
+                }
+                let p2pool = p2pool_state.clone();
+                match tauri::async_runtime::spawn_blocking(move || p2pool_status(&p2pool.data_api))
+                    .await
+                {
+                    Ok(Ok(status)) => events.publish(&Event::P2poolStatus(status)),
+                    // P2Pool only writes its statistics once it has synchronized the sidechain.
+                    Ok(Err(e)) => debug!("P2Pool statistics unavailable: {}", e),
+                    Err(e) => debug!("Failed to read P2Pool's statistics: {}", e),
+                }
+            }
+        });
+    } else {
+        info!("Mining on a remote pool; not starting P2Pool");
+    }
+    Ok(())
+}
+
+fn p2pool_status(data_api: &DataApi) -> Result<Status> {
+    Ok(Status::new(
+        &data_api.local_stratum()?,
+        &data_api.pool_stats()?,
+        &data_api.network_stats()?,
+    ))
+}
+
+/// Stop P2Pool, asking it to exit before resorting to signals.
+pub async fn stop_p2pool(state: &P2poolState) -> StopResult {
+    state
+        .process
+        .stop(state.process.write(b"exit\n"), Duration::from_secs(10))
+        .await
+}
+
+#[derive(Debug)]
+pub struct P2poolState {
+    pub process: Arc<Supervisor>,
+    pub data_api: DataApi,
+}
+
+impl P2poolState {
+    pub fn new(data_api_dir: PathBuf) -> P2poolState {
+        P2poolState {
+            process: Arc::new(Supervisor::new(Component::P2pool)),
+            data_api: DataApi::new(data_api_dir),
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    io::ErrorKind,
+    net::{Ipv4Addr, Ipv6Addr, TcpListener},
+};
+
+use anyhow::{Error, Result};
+use log::info;
+use serde::Serialize;
+
+use crate::{
+    config::{
+        daemon::Daemon,
+        pool::{LocalPool, Pool},
+        port::{self, Port},
+        Profile,
+    },
+    supervisor::Component,
+    MinistoState,
+};
+
+/// How many ports after the preferred one are tried for `"auto"`, before letting the OS choose.
+const AUTO_RANGE: u16 = 100;
+
+/// Ports our processes listen on, with `"auto"` resolved. Every client of our processes, including
+/// the other processes, connects using these.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Ports {
+    pub monerod_rpc: u16,
+    pub monerod_p2p: u16,
+    pub monerod_zmq: u16,
+    pub p2pool_stratum: u16,
+    pub xmrig_api: u16,
+}
+
+impl Default for Ports {
+    fn default() -> Self {
+        Ports {
+            monerod_rpc: port::MONEROD_RPC,
+            monerod_p2p: port::MONEROD_P2P,
+            monerod_zmq: port::MONEROD_ZMQ,
+            p2pool_stratum: port::P2POOL_STRATUM,
+            xmrig_api: port::XMRIG_API,
+        }
+    }
+}
+
+/// One port a process listens on.
+struct Listener<'a> {
+    component: Component,
+    name: &'static str,
+    setting: Port,
+    preferred: u16,
+    port: &'a mut u16,
+}
+
+/// Choose ports for the processes in `starting`, checking that fixed ports are free and picking
+/// free ports for `"auto"`. Ports of our processes in `running` are kept, and never handed out
+/// again.
+fn allocate(
+    profile: &Profile,
+    starting: &[Component],
+    running: &[Component],
+    current: Ports,
+    is_free: impl Fn(u16) -> bool,
+) -> Result<Ports> {
+    let mut ports = current;
+    let Ports {
+        monerod_rpc,
+        monerod_p2p,
+        monerod_zmq,
+        p2pool_stratum,
+        xmrig_api,
+    } = &mut ports;
+
+    let mut listeners = Vec::new();
+    if let Pool::Local(LocalPool {
+        daemon,
+        stratum_port,
+        ..
+    }) = &profile.pool
+    {
+        if let Daemon::Local(daemon) = daemon {
+            listeners.extend([
+                Listener {
+                    component: Component::Monerod,
+                    name: "RPC",
+                    setting: daemon.rpc_port,
+                    preferred: port::MONEROD_RPC,
+                    port: monerod_rpc,
+                },
+                Listener {
+                    component: Component::Monerod,
+                    name: "P2P",
+                    setting: daemon.p2p_port,
+                    preferred: port::MONEROD_P2P,
+                    port: monerod_p2p,
+                },
+                Listener {
+                    component: Component::Monerod,
+                    name: "ZMQ",
+                    setting: daemon.zmq_port,
+                    preferred: port::MONEROD_ZMQ,
+                    port: monerod_zmq,
+                },
+            ]);
+        }
+        listeners.push(Listener {
+            component: Component::P2pool,
+            name: "stratum",
+            setting: *stratum_port,
+            preferred: port::P2POOL_STRATUM,
+            port: p2pool_stratum,
+        });
+    }
+    listeners.push(Listener {
+        component: Component::Xmrig,
+        name: "API",
+        setting: profile.xmrig.api_port,
+        preferred: port::XMRIG_API,
+        port: xmrig_api,
+    });
+
+    // Ports held by our processes that keep running, and by whoever was allocated a port already.
+    let mut taken: Vec<(u16, Component, &'static str)> = listeners
+        .iter()
+        .filter(|listener| running.contains(&listener.component))
+        .map(|listener| (*listener.port, listener.component, listener.name))
+        .collect();
+
+    for listener in listeners
+        .iter_mut()
+        .filter(|listener| starting.contains(&listener.component))
+    {
+        let chosen = match listener.setting {
+            Port::Fixed(port) => {
+                if let Some((_, owner, name)) = taken.iter().find(|(taken, ..)| *taken == port) {
+                    return Err(Error::msg(format!(
+                        "Port {} for {}'s {} is also used for {}'s {}",
+                        port, listener.component, listener.name, owner, name
+                    )));
+                }
+                if !is_free(port) {
+                    return Err(Error::msg(format!(
+                        "Port {} for {}'s {} is already in use by another program. Stop \
+                        it, choose another port, or set the port to \"auto\"",
+                        port, listener.component, listener.name
+                    )));
+                }
+                port
+            }
+            Port::Auto => (listener.preferred..=listener.preferred.saturating_add(AUTO_RANGE))
+                .find(|port| !taken.iter().any(|(taken, ..)| taken == port) && is_free(*port))
+                .or_else(|| {
+                    os_assigned_port().filter(|port| !taken.iter().any(|(t, ..)| t == port))
+                })
+                .ok_or_else(|| {
+                    Error::msg(format!(
+                        "No free port found for {}'s {}",
+                        listener.component, listener.name
+                    ))
+                })?,
+        };
+        taken.push((chosen, listener.component, listener.name));
+        *listener.port = chosen;
+    }
+    Ok(ports)
+}
+
+/// Whether nothing is listening on `port`, on any interface.
+fn is_free(port: u16) -> bool {
+    TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).is_ok()
+        && TcpListener::bind((Ipv4Addr::LOCALHOST, port)).is_ok()
+        && match TcpListener::bind((Ipv6Addr::UNSPECIFIED, port)) {
+            Ok(_) => true,
+            // Any other error means there's no IPv6 to listen on.
+            Err(e) => e.kind() != ErrorKind::AddrInUse,
+        }
+}
+
+/// A port the OS considers free.
+fn os_assigned_port() -> Option<u16> {
+    let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    Some(listener.local_addr().ok()?.port())
+}
+
+/// Choose the ports for the processes about to be started, and record them in `state.ports`.
+pub async fn allocate_ports(state: &MinistoState, starting: &[Component]) -> Result<Ports> {
+    let ports = plan_ports(state, starting).await?;
+    *state.ports.lock().expect("ports lock poisoned") = ports;
+    info!("Using ports {:?}", ports);
+    Ok(ports)
+}
+
+/// The ports the processes in `starting` would be started with, without recording them.
+pub async fn plan_ports(state: &MinistoState, starting: &[Component]) -> Result<Ports> {
+    let mut running = Vec::new();
+    for (component, process) in [
+        (Component::Monerod, &state.monerod.process),
+        (Component::P2pool, &state.p2pool.process),
+        (Component::Xmrig, &state.xmrig.process),
+    ] {
+        if !starting.contains(&component) && process.is_active().await {
+            running.push(component);
+        }
+    }
+
+    let config = state.config.lock().await;
+    let current = *state.ports.lock().expect("ports lock poisoned");
+    allocate(config.profile(), starting, &running, current, is_free)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::daemon::LocalDaemon;
+
+    const ALL: [Component; 3] = [Component::Monerod, Component::P2pool, Component::Xmrig];
+
+    fn profile(daemon: LocalDaemon, stratum_port: Port, api_port: Port) -> Profile {
+        let mut profile = Profile {
+            pool: Pool::Local(LocalPool {
+                daemon: Daemon::Local(daemon),
+                stratum_port,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        profile.xmrig.api_port = api_port;
+        profile
+    }
+
+    #[test]
+    fn defaults_when_free() {
+        let profile = Profile::default();
+        let ports = allocate(&profile, &ALL, &[], Ports::default(), |_| true).unwrap();
+        assert_eq!(ports, Ports::default());
+    }
+
+    #[test]
+    fn ports_in_use_over_ipv6() {
+        let listener = match TcpListener::bind((Ipv6Addr::LOCALHOST, 0)) {
+            Ok(listener) => listener,
+            // IPv6 isn't available here.
+            Err(_) => return,
+        };
+        assert!(!is_free(listener.local_addr().unwrap().port()));
+    }
+
+    #[test]
+    fn fixed_by_default() {
+        let profile = Profile::default();
+        let error = allocate(&profile, &ALL, &[], Ports::default(), |port| {
+            port != port::P2POOL_STRATUM
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("3333"), "{}", error);
+    }
+
+    #[test]
+    fn auto_skips_ports_in_use() {
+        let profile = profile(LocalDaemon::default(), Port::Auto, Port::Auto);
+        let ports = allocate(&profile, &ALL, &[], Ports::default(), |port| {
+            port != port::P2POOL_STRATUM && port != port::XMRIG_API
+        })
+        .unwrap();
+        // 3334 is in use too, and 3335 went to P2Pool first.
+        assert_eq!(ports.p2pool_stratum, 3335);
+        assert_eq!(ports.xmrig_api, 3336);
+    }
+
+    #[test]
+    fn fixed_ports_must_be_free() {
+        let profile = Profile::default();
+        let error = allocate(&profile, &ALL, &[], Ports::default(), |port| {
+            port != port::MONEROD_RPC
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("already in use"), "{}", error);
+    }
+
+    #[test]
+    fn fixed_ports_conflicting_with_each_other() {
+        let profile = profile(
+            LocalDaemon::default(),
+            Port::Fixed(port::MONEROD_RPC),
+            Port::Auto,
+        );
+        let error = allocate(&profile, &ALL, &[], Ports::default(), |_| true).unwrap_err();
+        assert!(error.to_string().contains("Monerod's RPC"), "{}", error);
+    }
+
+    #[test]
+    fn keeps_ports_of_running_processes() {
+        let profile = profile(LocalDaemon::default(), Port::Auto, Port::Auto);
+        let current = Ports {
+            p2pool_stratum: 3334,
+            ..Ports::default()
+        };
+        // Only XMRig restarts, and our own P2Pool holds 3334, so nothing is free by then.
+        let ports = allocate(
+            &profile,
+            &[Component::Xmrig],
+            &[Component::Monerod, Component::P2pool],
+            current,
+            |port| port != 3334,
+        )
+        .unwrap();
+        assert_eq!(ports.p2pool_stratum, 3334);
+        assert_eq!(ports.xmrig_api, 3335);
+    }
+}
@@ -0,0 +1,554 @@
+use std::{
+    env::consts::EXE_SUFFIX,
+    error, fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Error, Result};
+use serde::Serialize;
+use tauri::{command, utils::platform::current_exe, State};
+
+#[cfg(target_os = "linux")]
+use crate::xmrig::tuning::HUGE_PAGES_WANTED;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+use crate::xmrig::tuning::MSR_TUNED_MARKER;
+use crate::{
+    address::validate_payout_address,
+    config::{
+        daemon::{Daemon, LocalDaemon},
+        pool::{LocalPool, Pool},
+        xmrig::XmrigPrivileges,
+        Profile,
+    },
+    ports::plan_ports,
+    startup::launched,
+    supervisor::Component,
+    MinistoState,
+};
+
+const GB: u64 = 1_000_000_000;
+/// Space a pruned blockchain needs, with some room to grow.
+const PRUNED_BLOCKCHAIN_SIZE: u64 = 100 * GB;
+/// Space a full blockchain needs, with some room to grow.
+const FULL_BLOCKCHAIN_SIZE: u64 = 250 * GB;
+
+/// What a pre-flight check looked at.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckKind {
+    Binaries,
+    DaemonSettings,
+    BlockchainDir,
+    DiskSpace,
+    Address,
+    Ports,
+    XmrigSettings,
+    Schedule,
+    HugePages,
+    Msr,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    Pass,
+    /// Mining works, but not as well as it could.
+    Warning,
+    /// Mining can't start until this is fixed.
+    Error,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Check {
+    pub kind: CheckKind,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Check {
+    fn pass(kind: CheckKind, message: impl Into<String>) -> Check {
+        Check {
+            kind,
+            severity: Severity::Pass,
+            message: message.into(),
+        }
+    }
+
+    fn warning(kind: CheckKind, message: impl Into<String>) -> Check {
+        Check {
+            kind,
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+
+    fn error(kind: CheckKind, message: impl Into<String>) -> Check {
+        Check {
+            kind,
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+}
+
+/// Results of checking whether mining can start with the active profile.
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightReport {
+    pub checks: Vec<Check>,
+}
+
+impl PreflightReport {
+    /// Whether nothing stands in the way of mining.
+    pub fn passed(&self) -> bool {
+        self.with_severity(Severity::Error).next().is_none()
+    }
+
+    pub fn with_severity(&self, severity: Severity) -> impl Iterator<Item = &Check> {
+        self.checks
+            .iter()
+            .filter(move |check| check.severity == severity)
+    }
+}
+
+/// Mining was refused because of the pre-flight checks.
+#[derive(Debug)]
+pub struct PreflightError(pub PreflightReport);
+
+impl fmt::Display for PreflightError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors: Vec<&str> = self
+            .0
+            .with_severity(Severity::Error)
+            .map(|check| check.message.as_str())
+            .collect();
+        write!(f, "Pre-flight checks failed: {}", errors.join("; "))
+    }
+}
+
+impl error::Error for PreflightError {}
+
+/// Check everything mining with the active profile relies on.
+pub async fn preflight(state: &MinistoState) -> PreflightReport {
+    let profile = state.config.lock().await.profile().clone();
+
+    let mut checks = Vec::new();
+    checks.push(match sidecar_dir() {
+        Ok(dir) => check_binaries(&profile, &dir),
+        Err(e) => Check::error(CheckKind::Binaries, e.to_string()),
+    });
+    if let Pool::Local(LocalPool {
+        daemon: Daemon::Local(daemon),
+        ..
+    }) = &profile.pool
+    {
+        checks.extend(check_local_daemon(daemon));
+    }
+    checks.push(check_address(&profile.pool));
+    checks.push(
+        // Only for the processes this profile runs, so that a node of the user's own doesn't get in
+        // the way of mining on a remote pool.
+        match plan_ports(state, &launched(&profile)).await {
+            Ok(_) => Check::pass(CheckKind::Ports, "All ports are available"),
+            Err(e) => Check::error(CheckKind::Ports, e.to_string()),
+        },
+    );
+    checks.push(match profile.xmrig.validate() {
+        Ok(()) => Check::pass(CheckKind::XmrigSettings, "XMRig's settings are valid"),
+        Err(e) => Check::error(CheckKind::XmrigSettings, e.to_string()),
+    });
+    if profile.schedule.enabled {
+        checks.push(match profile.schedule.validate() {
+            Ok(()) => Check::pass(CheckKind::Schedule, "The mining schedule is valid"),
+            Err(e) => Check::error(CheckKind::Schedule, e.to_string()),
+        });
+    }
+    checks.extend(check_huge_pages(profile.xmrig.privileges));
+    checks.extend(check_msr(profile.xmrig.privileges));
+    PreflightReport { checks }
+}
+
+/// Check whether mining can start with the active profile, without starting it.
+#[command(async)]
+pub async fn preflight_check(state: State<'_, MinistoState>) -> Result<PreflightReport, String> {
+    Ok(preflight(&state).await)
+}
+
+/// Where Tauri puts the binaries bundled with Ministo.
+fn sidecar_dir() -> Result<PathBuf> {
+    current_exe()?
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| Error::msg("Failed to determine where Ministo is installed"))
+}
+
+/// Check that the programs the profile runs are installed, and can be run.
+fn check_binaries(profile: &Profile, dir: &Path) -> Check {
+    for component in launched(profile) {
+        let name = match component {
+            Component::Monerod => "monerod",
+            Component::P2pool => "p2pool",
+            Component::Xmrig => "xmrig",
+        };
+        let path = dir.join(format!("{}{}", name, EXE_SUFFIX));
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => {
+                return Check::error(
+                    CheckKind::Binaries,
+                    format!("{} is missing from {}", component, dir.display()),
+                )
+            }
+        };
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            if metadata.permissions().mode() & 0o111 == 0 {
+                return Check::error(
+                    CheckKind::Binaries,
+                    format!("{} is not executable", path.display()),
+                );
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = metadata;
+    }
+    Check::pass(CheckKind::Binaries, "Everything needed is installed")
+}
+
+/// Check the settings monerod would be started with, and where it would store the blockchain.
+fn check_local_daemon(daemon: &LocalDaemon) -> Vec<Check> {
+    if let Err(e) = daemon.validate() {
+        // The blockchain directory may not even be set.
+        return vec![Check::error(CheckKind::DaemonSettings, e.to_string())];
+    }
+    let mut checks = vec![Check::pass(
+        CheckKind::DaemonSettings,
+        "Monerod's settings are valid",
+    )];
+
+    let dir = Path::new(&daemon.blockchain_dir);
+    if let Err(e) = check_writable(dir) {
+        checks.push(Check::error(
+            CheckKind::BlockchainDir,
+            format!("Can't store the blockchain in {}: {}", dir.display(), e),
+        ));
+        return checks;
+    }
+    checks.push(Check::pass(
+        CheckKind::BlockchainDir,
+        format!("The blockchain will be stored in {}", dir.display()),
+    ));
+
+    let needed = if daemon.prune {
+        PRUNED_BLOCKCHAIN_SIZE
+    } else {
+        FULL_BLOCKCHAIN_SIZE
+    };
+    // Whatever has been synchronized already doesn't need space again.
+    let existing = fs::metadata(dir.join("lmdb").join("data.mdb")).map_or(0, |m| m.len());
+    checks.push(match fs2::available_space(existing_ancestor(dir)) {
+        Ok(available) => check_disk_space(needed, existing, available),
+        Err(e) => Check::warning(
+            CheckKind::DiskSpace,
+            format!("Failed to determine free space: {}", e),
+        ),
+    });
+    checks
+}
+
+/// Check that `dir` can be written to, or created if it doesn't exist yet. Monerod creates it
+/// itself, so nothing is created here.
+fn check_writable(dir: &Path) -> Result<()> {
+    let existing = existing_ancestor(dir);
+    if !existing.is_dir() {
+        return Err(Error::msg(format!("{} isn't a folder", existing.display())));
+    }
+    let probe = existing.join(".ministo-write-test");
+    fs::write(&probe, b"")?;
+    fs::remove_file(&probe)?;
+    Ok(())
+}
+
+/// `dir` if it exists, or else the closest folder above it that does.
+fn existing_ancestor(dir: &Path) -> &Path {
+    dir.ancestors()
+        .find(|dir| dir.exists())
+        .unwrap_or_else(|| Path::new("."))
+}
+
+fn check_disk_space(needed: u64, existing: u64, available: u64) -> Check {
+    let remaining = needed.saturating_sub(existing);
+    if available < remaining {
+        Check::error(
+            CheckKind::DiskSpace,
+            format!(
+                "The blockchain needs about {} GB more space, but only {} GB is free",
+                remaining / GB,
+                available / GB
+            ),
+        )
+    } else {
+        Check::pass(
+            CheckKind::DiskSpace,
+            format!("{} GB is free", available / GB),
+        )
+    }
+}
+
+/// Check that there's an address to mine to.
+fn check_address(pool: &Pool) -> Check {
+    match pool {
+        Pool::Local(LocalPool { monero_address, .. }) => {
+            match monero_address.as_deref().map(validate_payout_address) {
+                None => Check::error(CheckKind::Address, "No Monero address is configured"),
+                Some(Err(e)) => Check::error(CheckKind::Address, e.to_string()),
+                Some(Ok(_)) => Check::pass(CheckKind::Address, "The Monero address is valid"),
+            }
+        }
+        Pool::Remote(pool) if pool.ip.is_empty() => {
+            Check::error(CheckKind::Address, "Remote pool address not configured")
+        }
+        Pool::Remote(pool) if pool.user.is_none() => Check::error(
+            CheckKind::Address,
+            "A wallet address or username is required to mine on a remote pool",
+        ),
+        Pool::Remote(_) => Check::pass(CheckKind::Address, "The remote pool is configured"),
+    }
+}
+
+/// How to set the system up for XMRig running unprivileged.
+#[cfg(target_os = "linux")]
+const TUNE_ADVICE: &str =
+    "Tune the system from the settings page, or run `sudo ministo --tune-system`";
+
+/// Check whether XMRig will be able to use huge pages, which speed RandomX up considerably.
+#[cfg(target_os = "linux")]
+fn check_huge_pages(privileges: XmrigPrivileges) -> Option<Check> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let (free_pages, page_size) = parse_huge_pages(&meminfo)?;
+    let free = free_pages * page_size;
+    Some(if free >= HUGE_PAGES_WANTED {
+        Check::pass(CheckKind::HugePages, "Enough huge pages are free")
+    } else {
+        Check::warning(
+            CheckKind::HugePages,
+            format!(
+                "Only {} MB of huge pages are free, of the {} MB XMRig uses. {}",
+                free / (1024 * 1024),
+                HUGE_PAGES_WANTED / (1024 * 1024),
+                match privileges {
+                    XmrigPrivileges::Root =>
+                        "XMRig will try to reserve more, but mines slower if it can't".to_string(),
+                    XmrigPrivileges::Unprivileged => format!(
+                        "XMRig can't reserve more without root, so it mines slower. {}",
+                        TUNE_ADVICE
+                    ),
+                }
+            ),
+        )
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn check_huge_pages(_privileges: XmrigPrivileges) -> Option<Check> {
+    None
+}
+
+/// Free huge pages, and their size in bytes, from the contents of `/proc/meminfo`.
+#[cfg(any(target_os = "linux", test))]
+pub fn parse_huge_pages(meminfo: &str) -> Option<(u64, u64)> {
+    let field = |name: &str| -> Option<u64> {
+        let line = meminfo.lines().find(|line| line.starts_with(name))?;
+        line[name.len()..]
+            .trim_start_matches(':')
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()
+    };
+    Some((field("HugePages_Free")?, field("Hugepagesize")? * 1024))
+}
+
+/// Check whether XMRig will be able to tune the CPU's model-specific registers for RandomX. Running
+/// unprivileged, they have to have been tuned since the last reboot.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn check_msr(privileges: XmrigPrivileges) -> Option<Check> {
+    let (ready, problem) = match privileges {
+        XmrigPrivileges::Root => (
+            Path::new("/dev/cpu/0/msr").exists(),
+            "The msr kernel module isn't loaded. XMRig will try to load it, but mines up to 15% \
+            slower if it can't"
+                .to_string(),
+        ),
+        XmrigPrivileges::Unprivileged => (
+            Path::new(MSR_TUNED_MARKER).exists(),
+            format!(
+                "The CPU's model-specific registers haven't been tuned since the last reboot, so \
+                XMRig mines up to 15% slower. {}",
+                TUNE_ADVICE
+            ),
+        ),
+    };
+    Some(if ready {
+        Check::pass(CheckKind::Msr, "Model-specific registers are available")
+    } else {
+        Check::warning(CheckKind::Msr, problem)
+    })
+}
+
+#[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+fn check_msr(_privileges: XmrigPrivileges) -> Option<Check> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::tempdir;
+
+    use crate::config::pool::RemotePool;
+
+    const ADDRESS: &str = "4A1WSBQdCbUCqt3DaGfmqVFchXScF43M6c5r4B6JXT3dUwuALncU9XTEnRPmUMcB3c16kVP9Y7thFLCJ5BaMW3UmSy93w3w";
+
+    #[test]
+    fn binaries() {
+        let dir = tempdir().unwrap();
+        let profile = Profile::default();
+        let check = check_binaries(&profile, dir.path());
+        assert_eq!(check.severity, Severity::Error);
+
+        for name in ["monerod", "p2pool", "xmrig"] {
+            let path = dir.path().join(format!("{}{}", name, EXE_SUFFIX));
+            fs::write(&path, b"").unwrap();
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+            }
+        }
+        assert_eq!(
+            check_binaries(&profile, dir.path()).severity,
+            Severity::Pass
+        );
+    }
+
+    #[test]
+    fn local_daemon() {
+        let dir = tempdir().unwrap();
+        let daemon = LocalDaemon {
+            blockchain_dir: dir.path().join("blockchain").to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        let checks = check_local_daemon(&daemon);
+        let kinds: Vec<CheckKind> = checks.iter().map(|check| check.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                CheckKind::DaemonSettings,
+                CheckKind::BlockchainDir,
+                CheckKind::DiskSpace
+            ]
+        );
+        assert_eq!(checks[1].severity, Severity::Pass);
+        // A dry run leaves nothing behind.
+        assert!(!dir.path().join("blockchain").exists());
+
+        let invalid = LocalDaemon {
+            blockchain_dir: String::new(),
+            ..Default::default()
+        };
+        assert_eq!(check_local_daemon(&invalid)[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn writable_without_creating() {
+        let dir = tempdir().unwrap();
+        let blockchain = dir.path().join("monero").join("blockchain");
+        assert!(check_writable(&blockchain).is_ok());
+        assert!(!dir.path().join("monero").exists());
+
+        let file = dir.path().join("file");
+        fs::write(&file, b"").unwrap();
+        assert!(check_writable(&file.join("blockchain")).is_err());
+    }
+
+    #[test]
+    fn disk_space() {
+        assert_eq!(
+            check_disk_space(100 * GB, 0, 50 * GB).severity,
+            Severity::Error
+        );
+        // Most of the blockchain has been synchronized already.
+        assert_eq!(
+            check_disk_space(100 * GB, 80 * GB, 50 * GB).severity,
+            Severity::Pass
+        );
+    }
+
+    #[test]
+    fn address() {
+        let local = |address: Option<&str>| {
+            Pool::Local(LocalPool {
+                monero_address: address.map(String::from),
+                ..Default::default()
+            })
+        };
+        assert_eq!(check_address(&local(None)).severity, Severity::Error);
+        assert_eq!(
+            check_address(&local(Some("4abc"))).severity,
+            Severity::Error
+        );
+        assert_eq!(
+            check_address(&local(Some(ADDRESS))).severity,
+            Severity::Pass
+        );
+
+        let remote = Pool::Remote(RemotePool {
+            ip: "pool.example.com".to_string(),
+            port: 443,
+            user: None,
+            password: None,
+            tls: true,
+            rig_id: None,
+            algorithm: None,
+            coin: None,
+        });
+        assert_eq!(check_address(&remote).severity, Severity::Error);
+    }
+
+    #[test]
+    fn huge_pages() {
+        let meminfo = "MemTotal:       32768000 kB\n\
+            HugePages_Total:     1280\n\
+            HugePages_Free:      1200\n\
+            Hugepagesize:       2048 kB\n";
+        assert_eq!(parse_huge_pages(meminfo), Some((1200, 2048 * 1024)));
+        assert_eq!(parse_huge_pages("MemTotal: 1 kB"), None);
+    }
+
+    #[test]
+    fn report() {
+        let mut report = PreflightReport {
+            checks: vec![
+                Check::pass(CheckKind::Ports, "All ports are available"),
+                Check::warning(CheckKind::Msr, "No MSR"),
+            ],
+        };
+        assert!(report.passed());
+        report
+            .checks
+            .push(Check::error(CheckKind::Address, "No address"));
+        report
+            .checks
+            .push(Check::error(CheckKind::Binaries, "No XMRig"));
+        assert!(!report.passed());
+        assert_eq!(
+            PreflightError(report).to_string(),
+            "Pre-flight checks failed: No address; No XMRig"
+        );
+    }
+}
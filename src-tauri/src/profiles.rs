@@ -0,0 +1,335 @@
+use anyhow::{Error, Result};
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{command, AppHandle, Manager, State};
+
+use crate::{
+    config::{
+        daemon::{Daemon, LocalDaemon},
+        pool::{LocalPool, Pool, RemotePool},
+        xmrig::XmrigConfig,
+        Profile,
+    },
+    monerod::stop_monerod,
+    p2pool::stop_p2pool,
+    ports::allocate_ports,
+    startup::start_in_order,
+    supervisor::{Component, StopResult},
+    xmrig::{needs_restart, reconfigure_xmrig, stop_xmrig},
+    MinistoState,
+};
+
+/// The profiles in the configuration, by name.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileList {
+    pub active: String,
+    pub names: Vec<String>,
+}
+
+#[command(async)]
+pub async fn list_profiles(state: State<'_, MinistoState>) -> Result<ProfileList, String> {
+    let config = state.config.lock().await;
+    Ok(ProfileList {
+        active: config.active_profile.clone(),
+        names: config.profiles.keys().cloned().collect(),
+    })
+}
+
+/// Create a profile with the default settings.
+#[command(async)]
+pub async fn create_profile(state: State<'_, MinistoState>, name: String) -> Result<(), String> {
+    let mut config = state.config.lock().await;
+    let mut updated = config.clone();
+    updated
+        .add_profile(&name, Profile::default())
+        .map_err(|e| e.to_string())?;
+    updated
+        .save(&state.config_path)
+        .map_err(|e| e.to_string())?;
+    *config = updated;
+    Ok(())
+}
+
+/// Create a profile with the same settings as the profile `from`.
+#[command(async)]
+pub async fn clone_profile(
+    state: State<'_, MinistoState>,
+    from: String,
+    name: String,
+) -> Result<(), String> {
+    let mut config = state.config.lock().await;
+    let profile = config
+        .profiles
+        .get(&from)
+        .cloned()
+        .ok_or_else(|| format!("There is no profile named '{}'", from))?;
+    let mut updated = config.clone();
+    updated
+        .add_profile(&name, profile)
+        .map_err(|e| e.to_string())?;
+    updated
+        .save(&state.config_path)
+        .map_err(|e| e.to_string())?;
+    *config = updated;
+    Ok(())
+}
+
+#[command(async)]
+pub async fn delete_profile(state: State<'_, MinistoState>, name: String) -> Result<(), String> {
+    let mut config = state.config.lock().await;
+    let mut updated = config.clone();
+    updated.remove_profile(&name).map_err(|e| e.to_string())?;
+    updated
+        .save(&state.config_path)
+        .map_err(|e| e.to_string())?;
+    *config = updated;
+    Ok(())
+}
+
+/// Switch to the profile `name`. If mining, only the processes whose settings differ between the
+/// profiles are restarted, in the background. XMRig is reconfigured instead, where it can be.
+#[command(async)]
+pub async fn activate_profile(
+    app: AppHandle,
+    state: State<'_, MinistoState>,
+    name: String,
+) -> Result<(), String> {
+    let starting = switch_profile(&state, &name)
+        .await
+        .map_err(|e| e.to_string())?;
+    if !starting.is_empty() {
+        tauri::async_runtime::spawn(async move {
+            // Errors are published as the `failed` phase.
+            let _ = start_in_order(&app.state::<MinistoState>(), &starting).await;
+        });
+    }
+    Ok(())
+}
+
+/// Switch to the profile `name`, stopping the processes it affects. Returns the processes to start
+/// again.
+async fn switch_profile(state: &MinistoState, name: &str) -> Result<Vec<Component>> {
+    // Nothing is started with a half-applied profile, by a startup or another switch, until this
+    // returns.
+    let mut generation = state.startup.hold().await;
+    let mining = state.monerod.process.is_active().await
+        || state.p2pool.process.is_active().await
+        || state.xmrig.process.is_active().await;
+    if mining {
+        // A startup in progress would otherwise carry on with the old profile.
+        *generation += 1;
+    }
+
+    let (affected, restart_xmrig) = {
+        let config = state.config.lock().await;
+        let mut updated = config.clone();
+        updated.activate_profile(name)?;
+        let affected = Affected::between(config.profile(), updated.profile());
+        // XMRig applies most of its settings without restarting.
+        let restart_xmrig =
+            affected.xmrig && needs_restart(&config.profile().xmrig, &updated.profile().xmrig);
+        (affected, restart_xmrig)
+    };
+
+    // Stop in reverse dependency order. The config isn't locked meanwhile, as monerod can take a
+    // minute to stop.
+    if mining {
+        if restart_xmrig {
+            stopped(Component::Xmrig, stop_xmrig(&state.xmrig).await)?;
+        }
+        if affected.p2pool {
+            stopped(Component::P2pool, stop_p2pool(&state.p2pool).await)?;
+        }
+        if affected.monerod {
+            stopped(Component::Monerod, stop_monerod(&state.monerod).await)?;
+        }
+    }
+
+    {
+        let mut config = state.config.lock().await;
+        // Settings may have been saved while stopping.
+        let mut updated = config.clone();
+        updated.activate_profile(name)?;
+        updated.save(&state.config_path)?;
+        *config = updated;
+    }
+    info!("Switched to profile '{}'", name);
+    if !mining {
+        return Ok(Vec::new());
+    }
+
+    info!("Restarting after switching profiles: {:?}", affected);
+    // Processes that aren't running yet, because mining was still starting, are started too.
+    let mut starting = Vec::new();
+    for (component, restart, process) in [
+        (Component::Monerod, affected.monerod, &state.monerod.process),
+        (Component::P2pool, affected.p2pool, &state.p2pool.process),
+        (Component::Xmrig, restart_xmrig, &state.xmrig.process),
+    ] {
+        if restart || !process.is_active().await {
+            starting.push(component);
+        }
+    }
+    let previous = *state.ports.lock().expect("ports lock poisoned");
+    let ports = allocate_ports(state, &starting).await?;
+    // XMRig takes the new settings, and follows P2Pool if it now accepts miners on another port.
+    if !starting.contains(&Component::Xmrig)
+        && (affected.xmrig || ports.p2pool_stratum != previous.p2pool_stratum)
+    {
+        if let Err(e) = reconfigure_xmrig(state).await {
+            warn!("Restarting XMRig, as it couldn't be reconfigured: {}", e);
+            stop_xmrig(&state.xmrig).await;
+            starting.push(Component::Xmrig);
+        }
+    }
+    Ok(starting)
+}
+
+/// The profile isn't switched unless everything it affects has stopped.
+fn stopped(component: Component, result: StopResult) -> Result<()> {
+    match result {
+        StopResult::Failed { error } => Err(Error::msg(format!(
+            "Failed to stop {} to switch profiles: {}",
+            component, error
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Which processes need restarting to switch between two profiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Affected {
+    monerod: bool,
+    p2pool: bool,
+    xmrig: bool,
+}
+
+impl Affected {
+    fn between(old: &Profile, new: &Profile) -> Affected {
+        Affected {
+            monerod: local_daemon(old) != local_daemon(new),
+            p2pool: local_pool(old) != local_pool(new),
+            xmrig: xmrig_settings(old) != xmrig_settings(new),
+        }
+    }
+}
+
+/// The settings monerod is started with, if it's started at all.
+fn local_daemon(profile: &Profile) -> Option<&LocalDaemon> {
+    match &profile.pool {
+        Pool::Local(LocalPool {
+            daemon: Daemon::Local(daemon),
+            ..
+        }) => Some(daemon),
+        _ => None,
+    }
+}
+
+/// The settings P2Pool is started with, if it's started at all.
+fn local_pool(profile: &Profile) -> Option<&LocalPool> {
+    match &profile.pool {
+        Pool::Local(pool) => Some(pool),
+        Pool::Remote(_) => None,
+    }
+}
+
+/// The settings XMRig is started with. Any local pool is mined on the same way.
+fn xmrig_settings(profile: &Profile) -> (&XmrigConfig, Option<&RemotePool>, &[RemotePool]) {
+    let remote_pool = match &profile.pool {
+        Pool::Local(_) => None,
+        Pool::Remote(pool) => Some(pool),
+    };
+    (&profile.xmrig, remote_pool, &profile.failover_pools)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::{daemon::RemoteDaemon, pool::P2poolChain};
+
+    fn local(address: &str) -> Profile {
+        Profile {
+            pool: Pool::Local(LocalPool {
+                monero_address: Some(address.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unchanged() {
+        assert_eq!(
+            Affected::between(&local("4abc"), &local("4abc")),
+            Affected {
+                monerod: false,
+                p2pool: false,
+                xmrig: false
+            }
+        );
+    }
+
+    #[test]
+    fn p2pool_settings_only_restart_p2pool() {
+        let mut mini = local("4abc");
+        if let Pool::Local(pool) = &mut mini.pool {
+            pool.chain = P2poolChain::Mini;
+        }
+        assert_eq!(
+            Affected::between(&local("4abc"), &mini),
+            Affected {
+                monerod: false,
+                p2pool: true,
+                xmrig: false
+            }
+        );
+    }
+
+    #[test]
+    fn remote_daemon_stops_monerod() {
+        let mut lan_node = local("4abc");
+        if let Pool::Local(pool) = &mut lan_node.pool {
+            pool.daemon = Daemon::Remote(RemoteDaemon {
+                ip: "192.168.1.10".to_string(),
+                rpc_port: 18081,
+                zmq_port: 18083,
+                rpc_login: None,
+            });
+        }
+        assert_eq!(
+            Affected::between(&local("4abc"), &lan_node),
+            Affected {
+                monerod: true,
+                p2pool: true,
+                xmrig: false
+            }
+        );
+    }
+
+    #[test]
+    fn remote_pool_restarts_everything() {
+        let remote = Profile {
+            pool: Pool::Remote(RemotePool {
+                ip: "pool.example.com".to_string(),
+                port: 443,
+                user: Some("4abc".to_string()),
+                password: None,
+                tls: true,
+                rig_id: None,
+                algorithm: None,
+                coin: None,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            Affected::between(&local("4abc"), &remote),
+            Affected {
+                monerod: true,
+                p2pool: true,
+                xmrig: true
+            }
+        );
+    }
+}
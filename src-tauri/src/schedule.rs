@@ -0,0 +1,381 @@
+use std::{sync::Mutex as StdMutex, time::Duration};
+
+use chrono::{Datelike, Days, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use log::{info, warn};
+use serde::Serialize;
+use tauri::{command, State};
+use tokio::time::interval;
+
+use crate::{
+    config::schedule::{MiningWindow, Timezone},
+    events::{Event, MinistoEvents},
+    MinistoState,
+};
+
+/// How often to check whether a window has started or ended.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Where the active profile's schedule is at.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleStatus {
+    pub enabled: bool,
+    /// Whether the schedule allows mining right now.
+    pub mining: bool,
+    /// When mining is next resumed or paused, in seconds since the Unix epoch.
+    pub next_transition: Option<i64>,
+    /// Mining was paused or resumed by hand, which holds until the next transition.
+    pub overridden: bool,
+}
+
+/// Follows the active profile's schedule.
+#[derive(Debug, Default)]
+pub struct Schedule {
+    status: StdMutex<ScheduleStatus>,
+}
+
+impl Schedule {
+    pub fn status(&self) -> ScheduleStatus {
+        self.status
+            .lock()
+            .expect("schedule status lock poisoned")
+            .clone()
+    }
+
+    /// Whether mining may go on, as far as the schedule is concerned.
+    pub fn allows_mining(&self) -> bool {
+        let status = self.status();
+        !status.enabled || status.mining || status.overridden
+    }
+
+    /// Publish `status` if it changed.
+    fn update(&self, status: ScheduleStatus, events: &dyn MinistoEvents) {
+        let mut current = self.status.lock().expect("schedule status lock poisoned");
+        if *current != status {
+            *current = status.clone();
+            events.publish(&Event::ScheduleStatus(status));
+        }
+    }
+
+    /// Leave mining as it was paused or resumed by hand until the next transition.
+    pub fn override_until_next_transition(&self, events: &dyn MinistoEvents) {
+        let status = self.status();
+        if status.enabled {
+            self.update(
+                ScheduleStatus {
+                    overridden: true,
+                    ..status
+                },
+                events,
+            );
+        }
+    }
+}
+
+#[command(async)]
+pub async fn schedule_status(state: State<'_, MinistoState>) -> Result<ScheduleStatus, String> {
+    Ok(state.schedule.status())
+}
+
+/// Pause XMRig outside the active profile's mining windows, and resume it inside them. Runs until
+/// Ministo exits.
+pub async fn watch_schedule(state: &MinistoState) {
+    let mut interval = interval(POLL_INTERVAL);
+    // What the schedule last had XMRig do, if it's done anything since XMRig started.
+    let mut applied = None;
+    loop {
+        interval.tick().await;
+        let schedule = state.config.lock().await.profile().schedule.clone();
+        let status = if schedule.enabled {
+            let (mining, next) = schedule_at(&schedule.windows, now(schedule.timezone));
+            let previous = state.schedule.status();
+            ScheduleStatus {
+                enabled: true,
+                mining,
+                next_transition: next
+                    .and_then(|next| to_utc(schedule.timezone, next))
+                    .map(|next| next.timestamp()),
+                // Only until the schedule changes its mind.
+                overridden: previous.overridden && previous.enabled && previous.mining == mining,
+            }
+        } else {
+            ScheduleStatus::default()
+        };
+        state.schedule.update(status.clone(), &*state.events);
+
+        if !state.xmrig.process.is_running().await {
+            applied = None;
+            continue;
+        }
+        if status.overridden {
+            continue;
+        }
+        let wanted = !status.enabled || status.mining;
+        // Checked each time, as XMRig starts out mining again if it was restarted.
+        let paused = match state.xmrig.api.summary().await {
+            Ok(summary) => summary.paused,
+            Err(_) => continue,
+        };
+        let resume = match action(wanted, applied, paused) {
+            Some(resume) => resume,
+            None => continue,
+        };
+        let res = if resume {
+            state.xmrig.api.resume().await
+        } else {
+            state.xmrig.api.pause().await
+        };
+        match res {
+            Ok(()) => {
+                if resume {
+                    info!("Mining window started; resuming mining");
+                } else {
+                    info!("Outside of the mining windows; pausing mining");
+                }
+                applied = Some(resume);
+            }
+            Err(e) => warn!("Failed to keep mining to its schedule: {}", e),
+        }
+    }
+}
+
+/// Whether to resume (`Some(true)`) or pause (`Some(false)`) XMRig, if the schedule `wanted` it
+/// mining and it's `paused`. Only what the schedule paused itself, as last `applied`, is resumed, so
+/// that it doesn't undo idle mining.
+fn action(wanted: bool, applied: Option<bool>, paused: bool) -> Option<bool> {
+    if !wanted && !paused {
+        Some(false)
+    } else if wanted && paused && applied == Some(false) {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// Whether `windows` allow mining at `now`, and when that next changes, in the schedule's time
+/// zone. There's no next change if mining is allowed all the time, or never.
+fn schedule_at(windows: &[MiningWindow], now: NaiveDateTime) -> (bool, Option<NaiveDateTime>) {
+    let spans = spans(windows, now.date());
+    // The latest end of the windows `time` is in, if it's in any.
+    let end_of_windows_at = |time: NaiveDateTime| {
+        spans
+            .iter()
+            .filter(|(start, end)| *start <= time && time < *end)
+            .map(|(_, end)| *end)
+            .max()
+    };
+
+    match end_of_windows_at(now) {
+        Some(mut end) => {
+            // Windows may run into each other.
+            while let Some(later) = end_of_windows_at(end) {
+                end = later;
+            }
+            let week_later = now.checked_add_days(Days::new(7));
+            (true, Some(end).filter(|end| Some(*end) < week_later))
+        }
+        None => (
+            false,
+            spans
+                .iter()
+                .map(|(start, _)| *start)
+                .filter(|start| *start > now)
+                .min(),
+        ),
+    }
+}
+
+/// Start and end of each window from the day before `date` until a week after it.
+fn spans(windows: &[MiningWindow], date: NaiveDate) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+    let mut spans = Vec::new();
+    let first = date.pred_opt().unwrap_or(date);
+    for day in first.iter_days().take(9) {
+        for window in windows {
+            if !window.days.is_empty() && !window.days.contains(&day.weekday().into()) {
+                continue;
+            }
+            let end = if window.end > window.start {
+                day.and_time(window.end)
+            } else {
+                (day + Days::new(1)).and_time(window.end)
+            };
+            spans.push((day.and_time(window.start), end));
+        }
+    }
+    spans
+}
+
+fn now(timezone: Timezone) -> NaiveDateTime {
+    match timezone {
+        Timezone::Local => Local::now().naive_local(),
+        Timezone::Named(tz) => Utc::now().with_timezone(&tz).naive_local(),
+        Timezone::Fixed(offset) => Utc::now().with_timezone(&offset).naive_local(),
+    }
+}
+
+/// When `time` in the schedule's time zone happens. Times skipped by daylight saving time happen
+/// an hour later, and times that happen twice count the first time.
+fn to_utc(timezone: Timezone, time: NaiveDateTime) -> Option<chrono::DateTime<Utc>> {
+    let in_zone = |time: NaiveDateTime| match timezone {
+        Timezone::Local => Local
+            .from_local_datetime(&time)
+            .earliest()
+            .map(|time| time.with_timezone(&Utc)),
+        Timezone::Named(tz) => tz
+            .from_local_datetime(&time)
+            .earliest()
+            .map(|time| time.with_timezone(&Utc)),
+        Timezone::Fixed(offset) => offset
+            .from_local_datetime(&time)
+            .earliest()
+            .map(|time| time.with_timezone(&Utc)),
+    };
+    in_zone(time).or_else(|| in_zone(time + chrono::Duration::hours(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::NaiveTime;
+
+    use crate::config::schedule::Day;
+
+    fn at(date: (i32, u32, u32), hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(date.0, date.1, date.2)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn window(days: &[Day], start: u32, end: u32) -> MiningWindow {
+        MiningWindow {
+            days: days.to_vec(),
+            start: NaiveTime::from_hms_opt(start, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(end, 0, 0).unwrap(),
+        }
+    }
+
+    // 2024-01-01 was a Monday.
+    const MONDAY: (i32, u32, u32) = (2024, 1, 1);
+    const TUESDAY: (i32, u32, u32) = (2024, 1, 2);
+    const SATURDAY: (i32, u32, u32) = (2024, 1, 6);
+
+    #[test]
+    fn overnight_windows() {
+        let windows = [window(&[Day::Monday], 22, 7)];
+        assert_eq!(
+            schedule_at(&windows, at(MONDAY, 12, 0)),
+            (false, Some(at(MONDAY, 22, 0)))
+        );
+        assert_eq!(
+            schedule_at(&windows, at(MONDAY, 22, 0)),
+            (true, Some(at(TUESDAY, 7, 0)))
+        );
+        assert_eq!(
+            schedule_at(&windows, at(TUESDAY, 3, 0)),
+            (true, Some(at(TUESDAY, 7, 0)))
+        );
+        // Not until next Monday.
+        assert_eq!(
+            schedule_at(&windows, at(TUESDAY, 7, 0)),
+            (false, Some(at((2024, 1, 8), 22, 0)))
+        );
+    }
+
+    #[test]
+    fn windows_running_into_each_other() {
+        let windows = [window(&[], 22, 7), window(&[Day::Saturday], 7, 22)];
+        // Saturday's day window joins Friday's and Saturday's nights.
+        assert_eq!(
+            schedule_at(&windows, at(SATURDAY, 3, 0)),
+            (true, Some(at((2024, 1, 7), 7, 0)))
+        );
+        assert_eq!(
+            schedule_at(&windows, at(TUESDAY, 12, 0)),
+            (false, Some(at(TUESDAY, 22, 0)))
+        );
+
+        // Mining all the time, or never.
+        assert_eq!(
+            schedule_at(&[window(&[], 0, 0)], at(MONDAY, 12, 0)),
+            (true, None)
+        );
+        assert_eq!(schedule_at(&[], at(MONDAY, 12, 0)), (false, None));
+    }
+
+    #[test]
+    fn fixed_offsets() {
+        let timezone: Timezone = "+02:00".parse().unwrap();
+        assert_eq!(
+            to_utc(timezone, at(MONDAY, 22, 0)).unwrap().timestamp(),
+            Utc.from_utc_datetime(&at(MONDAY, 20, 0)).timestamp()
+        );
+    }
+
+    #[test]
+    fn pauses_xmrig_again_after_a_restart() {
+        // The window ends, and the schedule pauses XMRig.
+        assert_eq!(action(false, None, false), Some(false));
+        assert_eq!(action(false, Some(false), true), None);
+        // XMRig crashes and is restarted mid-window, mining again.
+        assert_eq!(action(false, Some(false), false), Some(false));
+        // The next window starts.
+        assert_eq!(action(true, Some(false), true), Some(true));
+        assert_eq!(action(true, Some(true), false), None);
+        // Idle mining pauses XMRig while the user is active, which is left alone.
+        assert_eq!(action(true, Some(true), true), None);
+        assert_eq!(action(true, None, true), None);
+    }
+
+    #[test]
+    fn daylight_saving_time() {
+        let london: Timezone = "Europe/London".parse().unwrap();
+        let utc = |date, hour, minute| Some(Utc.from_utc_datetime(&at(date, hour, minute)));
+
+        // Clocks went forward from 01:00 to 02:00 on 2024-03-31.
+        let windows = [window(&[Day::Saturday], 22, 7)];
+        let (mining, next) = schedule_at(&windows, at((2024, 3, 30), 23, 0));
+        assert!(mining);
+        assert_eq!(to_utc(london, next.unwrap()), utc((2024, 3, 31), 6, 0));
+        assert_eq!(
+            to_utc(london, at((2024, 3, 30), 22, 0)),
+            utc((2024, 3, 30), 22, 0)
+        );
+        // 01:30 never happened.
+        assert_eq!(
+            to_utc(london, at((2024, 3, 31), 1, 30)),
+            utc((2024, 3, 31), 1, 30)
+        );
+
+        // Clocks went back from 02:00 to 01:00 on 2024-10-27, so 01:30 happened twice.
+        assert_eq!(
+            to_utc(london, at((2024, 10, 27), 1, 30)),
+            utc((2024, 10, 27), 0, 30)
+        );
+        assert_eq!(
+            to_utc(london, at((2024, 10, 27), 7, 0)),
+            utc((2024, 10, 27), 7, 0)
+        );
+    }
+
+    #[test]
+    fn overrides_hold_until_the_next_transition() {
+        let schedule = Schedule::default();
+        let events = crate::events::MemoryEvents::default();
+        schedule.override_until_next_transition(&events);
+        assert!(!schedule.status().overridden);
+
+        schedule.update(
+            ScheduleStatus {
+                enabled: true,
+                ..Default::default()
+            },
+            &events,
+        );
+        assert!(!schedule.allows_mining());
+        schedule.override_until_next_transition(&events);
+        assert!(schedule.allows_mining());
+        assert_eq!(events.events().len(), 2);
+    }
+}
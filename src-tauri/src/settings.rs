@@ -1,9 +1,12 @@
+use log::warn;
+use serde::Serialize;
 use tauri::{api::dialog::FileDialogBuilder, command, State, Window};
 
 use crate::{
+    address::{validate_payout_address, AddressError},
     config::{
         daemon::{Daemon, LocalDaemon},
-        pool::{LocalPool, Pool},
+        pool::{LocalPool, Pool, RemotePool},
         Config,
     },
     MinistoState,
@@ -14,26 +17,60 @@ pub async fn save_settings(
     state: State<'_, MinistoState>,
     address: String,
     folder: String,
-) -> Result<(), String> {
-    let config_path = &state.config_path;
+) -> Result<(), SettingsError> {
     let mut config = state.config.lock().await;
+    let address = address.trim().to_string();
+    let mut updated = config.clone();
 
-    match &mut config.pool {
+    match &mut updated.profile_mut().pool {
         Pool::Local(LocalPool {
             monero_address,
-            daemon: Daemon::Local(LocalDaemon { blockchain_dir, .. }),
+            daemon,
             ..
         }) => {
+            // Catch mistakes now, rather than when P2Pool fails to start.
+            validate_payout_address(&address)?;
             *monero_address = Some(address);
-            *blockchain_dir = folder;
+            // The blockchain folder only applies when running our own daemon.
+            if let Daemon::Local(LocalDaemon { blockchain_dir, .. }) = daemon {
+                *blockchain_dir = folder;
+            }
         }
-        _ => Err("")?,
+        // Remote pools are mined on using the address as the username.
+        Pool::Remote(RemotePool { user, .. }) => *user = Some(address),
     }
 
-    config.save(config_path).map_err(|e| e.to_string())?;
+    updated
+        .save(&state.config_path)
+        .map_err(|e| SettingsError {
+            field: None,
+            message: e.to_string(),
+            address_error: None,
+        })?;
+    *config = updated;
     Ok(())
 }
 
+/// Why settings were rejected, as reported to the frontend.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsError {
+    /// The setting at fault, if it's down to one setting.
+    pub field: Option<&'static str>,
+    pub message: String,
+    pub address_error: Option<AddressError>,
+}
+
+impl From<AddressError> for SettingsError {
+    fn from(e: AddressError) -> Self {
+        SettingsError {
+            field: Some("address"),
+            message: e.to_string(),
+            address_error: Some(e),
+        }
+    }
+}
+
 #[command]
 pub async fn get_config(state: State<'_, MinistoState>) -> Result<Config, String> {
     let config = state.config.lock().await;
@@ -46,7 +83,7 @@ pub async fn select_blockchain_folder(window: Window) {
         if selected_path.is_some() {
             window
                 .emit("blockchain-folder-selected", selected_path)
-                .expect("failed to emit blockchain folder selected event")
+                .unwrap_or_else(|e| warn!("Failed to emit blockchain folder selected event: {}", e))
         }
     })
 }
@@ -0,0 +1,323 @@
+use std::{error, fmt, future::Future, sync::Mutex as StdMutex, time::Duration};
+
+use anyhow::{Error, Result};
+use log::{error, info};
+use serde::Serialize;
+use tauri::{command, State};
+use tokio::{
+    net::TcpStream,
+    sync::{Mutex, MutexGuard},
+    time::interval,
+};
+
+use crate::{
+    config::{
+        daemon::Daemon,
+        pool::{LocalPool, Pool},
+        Profile,
+    },
+    events::{Event, MinistoEvents},
+    monerod::start_monerod,
+    p2pool::start_p2pool,
+    supervisor::{Component, Supervisor},
+    xmrig::start_xmrig,
+    MinistoState,
+};
+
+/// How often to check whether a process is ready for the next one to be started.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Where starting to mine is at.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase", tag = "phase")]
+pub enum Phase {
+    #[default]
+    Stopped,
+    StartingMonerod,
+    /// Waiting for monerod to synchronize the blockchain, which can take hours the first time.
+    WaitingForSync,
+    StartingP2pool,
+    /// Waiting for P2Pool to accept miners.
+    WaitingForP2pool,
+    StartingXmrig,
+    Mining,
+    Failed {
+        message: String,
+    },
+}
+
+/// Follows the processes being started in order, so that stopping can cancel it.
+#[derive(Debug, Default)]
+pub struct Startup {
+    phase: StdMutex<Phase>,
+    /// Bumped whenever a startup begins or is cancelled. Held while starting a process, so that a
+    /// startup being cancelled can't start anything more.
+    generation: Mutex<u64>,
+}
+
+impl Startup {
+    pub fn phase(&self) -> Phase {
+        self.phase
+            .lock()
+            .expect("startup phase lock poisoned")
+            .clone()
+    }
+
+    /// Cancel the startup in progress, if any. Once this returns, it won't start anything else.
+    pub async fn cancel(&self) {
+        *self.generation.lock().await += 1;
+    }
+
+    /// Keep every startup from starting anything until the guard is dropped. Bumping the guarded
+    /// generation cancels the startup in progress.
+    pub async fn hold(&self) -> MutexGuard<'_, u64> {
+        self.generation.lock().await
+    }
+
+    async fn begin(&self) -> u64 {
+        let mut generation = self.generation.lock().await;
+        *generation += 1;
+        *generation
+    }
+}
+
+/// The startup was cancelled, by stopping or by another startup.
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Starting to mine was cancelled")
+    }
+}
+
+impl error::Error for Cancelled {}
+
+/// Where starting to mine is at, for pages opened after the last `startup-phase` event.
+#[command(async)]
+pub async fn startup_phase(state: State<'_, MinistoState>) -> Result<Phase, String> {
+    Ok(state.startup.phase())
+}
+
+/// Record and publish where starting to mine is at.
+pub fn set_phase(state: &MinistoState, phase: Phase) {
+    info!("Startup phase: {:?}", phase);
+    *state
+        .startup
+        .phase
+        .lock()
+        .expect("startup phase lock poisoned") = phase.clone();
+    state.events.publish(&Event::StartupPhase(phase));
+}
+
+/// Start `components` one after another: monerod first, then P2Pool once monerod has synchronized,
+/// then XMRig once P2Pool accepts miners. Cancels any startup already in progress.
+pub async fn start_in_order(state: &MinistoState, components: &[Component]) -> Result<()> {
+    let generation = state.startup.begin().await;
+    let result = run(state, generation, components).await;
+    match &result {
+        Ok(()) => set_phase(state, Phase::Mining),
+        // Whatever cancelled the startup has moved on to another phase.
+        Err(e) if e.is::<Cancelled>() => info!("{}", e),
+        Err(e) => {
+            error!("Failed to start mining: {}", e);
+            set_phase(
+                state,
+                Phase::Failed {
+                    message: e.to_string(),
+                },
+            );
+        }
+    }
+    result
+}
+
+/// The processes mining with `profile` runs, in the order they're started: monerod only for a local
+/// daemon, and neither monerod nor P2Pool for a remote pool.
+pub fn launched(profile: &Profile) -> Vec<Component> {
+    match &profile.pool {
+        Pool::Local(LocalPool {
+            daemon: Daemon::Local(_),
+            ..
+        }) => vec![Component::Monerod, Component::P2pool, Component::Xmrig],
+        Pool::Local(_) => vec![Component::P2pool, Component::Xmrig],
+        Pool::Remote(_) => vec![Component::Xmrig],
+    }
+}
+
+async fn run(state: &MinistoState, generation: u64, components: &[Component]) -> Result<()> {
+    let launched = launched(state.config.lock().await.profile());
+    let components: Vec<Component> = components
+        .iter()
+        .copied()
+        .filter(|component| launched.contains(component))
+        .collect();
+
+    if components.contains(&Component::Monerod) {
+        step(
+            state,
+            generation,
+            Phase::StartingMonerod,
+            start_monerod(state),
+        )
+        .await?;
+        set_phase(state, Phase::WaitingForSync);
+        wait_for_sync(state, generation).await?;
+    }
+    if components.contains(&Component::P2pool) {
+        step(
+            state,
+            generation,
+            Phase::StartingP2pool,
+            start_p2pool(state),
+        )
+        .await?;
+        set_phase(state, Phase::WaitingForP2pool);
+        let stratum_port = state
+            .ports
+            .lock()
+            .expect("ports lock poisoned")
+            .p2pool_stratum;
+        wait_until(state, generation, &state.p2pool.process, || {
+            accepting_connections(stratum_port)
+        })
+        .await?;
+    }
+    if components.contains(&Component::Xmrig) {
+        step(state, generation, Phase::StartingXmrig, start_xmrig(state)).await?;
+    }
+    Ok(())
+}
+
+/// Start a process, unless the startup has been cancelled.
+async fn step(
+    state: &MinistoState,
+    generation: u64,
+    phase: Phase,
+    start: impl Future<Output = Result<()>>,
+) -> Result<()> {
+    let current = state.startup.generation.lock().await;
+    if *current != generation {
+        return Err(Cancelled.into());
+    }
+    set_phase(state, phase);
+    start.await
+}
+
+/// Wait until monerod has synchronized the blockchain, and publishes ZMQ notifications.
+async fn wait_for_sync(state: &MinistoState, generation: u64) -> Result<()> {
+    let rpc = state.monerod.rpc();
+    wait_until(state, generation, &state.monerod.process, || async {
+        matches!(rpc.get_info().await, Ok(info) if info.synchronized && !info.busy_syncing)
+    })
+    .await?;
+    let zmq_port = state.ports.lock().expect("ports lock poisoned").monerod_zmq;
+    wait_until(state, generation, &state.monerod.process, || {
+        accepting_connections(zmq_port)
+    })
+    .await
+}
+
+/// Poll `ready` until it's true, giving up if the startup is cancelled or `process` stops.
+async fn wait_until<F, Fut>(
+    state: &MinistoState,
+    generation: u64,
+    process: &Supervisor,
+    mut ready: F,
+) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let mut interval = interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        if *state.startup.generation.lock().await != generation {
+            return Err(Cancelled.into());
+        }
+        if !process.is_active().await {
+            return Err(Error::msg(format!(
+                "{} stopped before it was ready",
+                process.component()
+            )));
+        }
+        if ready().await {
+            return Ok(());
+        }
+    }
+}
+
+/// Whether something on this machine accepts connections on `port`.
+async fn accepting_connections(port: u16) -> bool {
+    TcpStream::connect(("127.0.0.1", port)).await.is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    use crate::config::{daemon::RemoteDaemon, pool::RemotePool};
+
+    #[test]
+    fn phases() {
+        assert_eq!(
+            serde_json::to_value(Phase::WaitingForSync).unwrap(),
+            json!({"phase": "waitingForSync"})
+        );
+        assert_eq!(
+            serde_json::to_value(Phase::Failed {
+                message: "No XMRig".to_string()
+            })
+            .unwrap(),
+            json!({"phase": "failed", "message": "No XMRig"})
+        );
+    }
+
+    #[test]
+    fn launched_processes() {
+        let mut profile = Profile::default();
+        assert_eq!(
+            launched(&profile),
+            [Component::Monerod, Component::P2pool, Component::Xmrig]
+        );
+        if let Pool::Local(pool) = &mut profile.pool {
+            pool.daemon = Daemon::Remote(RemoteDaemon {
+                ip: "192.168.1.10".to_string(),
+                rpc_port: 18081,
+                zmq_port: 18083,
+                rpc_login: None,
+            });
+        }
+        assert_eq!(launched(&profile), [Component::P2pool, Component::Xmrig]);
+        profile.pool = Pool::Remote(RemotePool {
+            ip: "pool.example.com".to_string(),
+            port: 443,
+            user: Some("4abc".to_string()),
+            password: None,
+            tls: true,
+            rig_id: None,
+            algorithm: None,
+            coin: None,
+        });
+        assert_eq!(launched(&profile), [Component::Xmrig]);
+    }
+
+    #[tokio::test]
+    async fn detects_listening_ports() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(accepting_connections(port).await);
+        drop(listener);
+        assert!(!accepting_connections(port).await);
+    }
+
+    #[tokio::test]
+    async fn cancelling_bumps_generation() {
+        let startup = Startup::default();
+        let generation = startup.begin().await;
+        startup.cancel().await;
+        assert_ne!(*startup.generation.lock().await, generation);
+    }
+}
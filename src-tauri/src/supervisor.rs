@@ -0,0 +1,513 @@
+use std::{
+    fmt,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex as StdMutex,
+    },
+    time::Duration,
+};
+
+use anyhow::{Error, Result};
+use log::{error, info, warn};
+use serde::Serialize;
+use tauri::{
+    api::process::{CommandChild, CommandEvent},
+    async_runtime::Receiver,
+};
+use tokio::{
+    sync::{Mutex, Notify},
+    time::{sleep, Instant},
+};
+
+use crate::{
+    config::SupervisorConfig,
+    events::{Event, MinistoEvents},
+};
+
+/// A process is considered stable (and its crash count reset) once it has been running this long.
+const STABLE_UPTIME: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Component {
+    Monerod,
+    P2pool,
+    Xmrig,
+}
+
+impl fmt::Display for Component {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Component::Monerod => write!(f, "Monerod"),
+            Component::P2pool => write!(f, "P2Pool"),
+            Component::Xmrig => write!(f, "XMRig"),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", tag = "state")]
+pub enum ProcessState {
+    Stopped,
+    Running {
+        pid: u32,
+    },
+    /// The process exited unexpectedly and will be restarted after `restart_in_secs`.
+    #[serde(rename_all = "camelCase")]
+    Crashed {
+        exit_code: Option<i32>,
+        signal: Option<i32>,
+        crashes: u32,
+        restart_in_secs: u64,
+    },
+    /// The process crashed more often than the crash budget allows, and will not be restarted.
+    #[serde(rename_all = "camelCase")]
+    Failed {
+        exit_code: Option<i32>,
+        signal: Option<i32>,
+        crashes: u32,
+    },
+}
+
+/// Outcome of stopping a supervised process.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", tag = "result")]
+pub enum StopResult {
+    /// The process was not running to begin with.
+    NotRunning,
+    /// The process exited on request.
+    Exited,
+    /// The process had to be sent SIGTERM.
+    Terminated,
+    /// The process had to be killed.
+    Killed,
+    Failed {
+        error: String,
+    },
+}
+
+/// Keeps track of a sidecar process, restarting it with exponential backoff if it crashes.
+#[derive(Debug)]
+pub struct Supervisor {
+    component: Component,
+    child: Mutex<Option<CommandChild>>,
+    state: Mutex<ProcessState>,
+    stopping: AtomicBool,
+    /// Wakes the restart task from its backoff, so that it notices it was stopped. Replaced each
+    /// time the process is supervised, so that no wake-up is left over for the next run.
+    wake: StdMutex<Arc<Notify>>,
+}
+
+impl Supervisor {
+    pub fn new(component: Component) -> Supervisor {
+        Supervisor {
+            component,
+            child: Mutex::new(None),
+            state: Mutex::new(ProcessState::Stopped),
+            stopping: AtomicBool::new(false),
+            wake: StdMutex::new(Arc::new(Notify::new())),
+        }
+    }
+
+    pub fn component(&self) -> Component {
+        self.component
+    }
+
+    pub async fn is_running(&self) -> bool {
+        self.child.lock().await.is_some()
+    }
+
+    pub async fn pid(&self) -> Option<u32> {
+        self.child.lock().await.as_ref().map(|child| child.pid())
+    }
+
+    /// Whether the process is running, or is waiting to be restarted.
+    pub async fn is_active(&self) -> bool {
+        matches!(
+            *self.state.lock().await,
+            ProcessState::Running { .. } | ProcessState::Crashed { .. }
+        )
+    }
+
+    /// Write to the stdin of the supervised process.
+    pub async fn write(&self, buf: &[u8]) -> Result<()> {
+        match &mut *self.child.lock().await {
+            Some(child) => child.write(buf)?,
+            None => return Err(Error::msg(format!("{} is not running", self.component))),
+        }
+        Ok(())
+    }
+
+    /// Ask the supervised process to exit, with SIGTERM.
+    #[cfg(unix)]
+    pub async fn terminate(&self) -> Result<()> {
+        Ok(self.signal(libc::SIGTERM).await?)
+    }
+
+    /// Let the supervisor know that the next exit is intentional, and should not trigger a restart.
+    pub fn mark_stopping(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+    }
+
+    /// Stop the supervised process by awaiting `request_exit`, escalating to SIGTERM and then
+    /// SIGKILL if the process does not exit within `timeout`.
+    pub async fn stop<F>(&self, request_exit: F, timeout: Duration) -> StopResult
+    where
+        F: Future<Output = Result<()>>,
+    {
+        // Mark as stopping even if not running, so that pending restarts are cancelled.
+        self.mark_stopping();
+        if !self.is_running().await {
+            if matches!(*self.state.lock().await, ProcessState::Crashed { .. }) {
+                // Don't wait out the backoff for the restart to be cancelled.
+                self.wake.lock().expect("wake lock poisoned").notify_one();
+                while self.is_active().await {
+                    sleep(Duration::from_millis(10)).await;
+                }
+            }
+            return StopResult::NotRunning;
+        }
+
+        info!("Stopping {}", self.component);
+        match request_exit.await {
+            Ok(()) => {
+                if self.wait_for_exit(timeout).await {
+                    return StopResult::Exited;
+                }
+                warn!("{} did not exit within {:?}", self.component, timeout);
+            }
+            Err(e) => warn!("Failed to ask {} to exit: {}", self.component, e),
+        }
+
+        #[cfg(unix)]
+        {
+            info!("Sending SIGTERM to {}", self.component);
+            match self.signal(libc::SIGTERM).await {
+                Ok(()) => {
+                    if self.wait_for_exit(timeout).await {
+                        return StopResult::Terminated;
+                    }
+                }
+                // Killing it would fail too, such as when it runs as another user.
+                Err(e) if e.raw_os_error() == Some(libc::EPERM) => {
+                    return StopResult::Failed {
+                        error: format!("Not allowed to stop {}: {}", self.component, e),
+                    };
+                }
+                Err(e) => warn!("Failed to send SIGTERM to {}: {}", self.component, e),
+            }
+        }
+
+        warn!("Killing {}", self.component);
+        if let Some(child) = self.child.lock().await.take() {
+            if let Err(e) = child.kill() {
+                return StopResult::Failed {
+                    error: e.to_string(),
+                };
+            }
+        }
+        StopResult::Killed
+    }
+
+    /// Whether the process exits within `timeout`.
+    pub async fn wait_for_exit(&self, timeout: Duration) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < timeout {
+            if !self.is_running().await {
+                return true;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+        !self.is_running().await
+    }
+
+    #[cfg(unix)]
+    async fn signal(&self, signal: libc::c_int) -> std::io::Result<()> {
+        let pid = match &*self.child.lock().await {
+            Some(child) => child.pid(),
+            None => return Ok(()),
+        };
+        // SAFETY: `kill` does not touch any memory owned by this process.
+        if unsafe { libc::kill(pid as libc::pid_t, signal) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Spawn a process using `spawn`, and keep it running until it exits cleanly or is stopped.
+    ///
+    /// `spawn` is called again each time the process needs to be restarted, and every line the
+    /// process writes to stdout or stderr is passed to `on_output`.
+    pub async fn supervise<S, O>(
+        self: &Arc<Self>,
+        config: SupervisorConfig,
+        events: Arc<dyn MinistoEvents>,
+        spawn: S,
+        on_output: O,
+    ) -> Result<()>
+    where
+        S: Fn() -> Result<(Receiver<CommandEvent>, CommandChild)> + Send + 'static,
+        O: Fn(String) + Send + 'static,
+    {
+        self.stopping.store(false, Ordering::SeqCst);
+        let wake = Arc::new(Notify::new());
+        *self.wake.lock().expect("wake lock poisoned") = wake.clone();
+        let (mut rx, child) = spawn()?;
+        self.set_running(child, &*events).await;
+
+        let supervisor = self.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut crashes = 0;
+            let mut started = Instant::now();
+            loop {
+                // Read stdout until the process terminates.
+                let mut exit = None;
+                while let Some(event) = rx.recv().await {
+                    match event {
+                        CommandEvent::Stdout(line) | CommandEvent::Stderr(line) => on_output(line),
+                        CommandEvent::Terminated(payload) => {
+                            exit = Some((payload.code, payload.signal));
+                            break;
+                        }
+                        _ => continue,
+                    }
+                }
+                let (exit_code, signal) = exit.unwrap_or((None, None));
+                *supervisor.child.lock().await = None;
+
+                if supervisor.stopping.load(Ordering::SeqCst) || exit_code == Some(0) {
+                    info!("{} exited with code {:?}", supervisor.component, exit_code);
+                    supervisor.set_state(ProcessState::Stopped, &*events).await;
+                    return;
+                }
+
+                if started.elapsed() >= STABLE_UPTIME {
+                    crashes = 0;
+                }
+                crashes += 1;
+                warn!(
+                    "{} crashed with code {:?} (signal {:?}); crash {} of {}",
+                    supervisor.component, exit_code, signal, crashes, config.max_restarts
+                );
+
+                if crashes > config.max_restarts {
+                    error!(
+                        "{} exceeded its crash budget; giving up",
+                        supervisor.component
+                    );
+                    let state = ProcessState::Failed {
+                        exit_code,
+                        signal,
+                        crashes,
+                    };
+                    supervisor.set_state(state, &*events).await;
+                    return;
+                }
+
+                // Wait before restarting, doubling the wait after each consecutive crash.
+                let backoff = config
+                    .initial_backoff_secs
+                    .saturating_mul(1u64 << (crashes - 1).min(16))
+                    .min(config.max_backoff_secs);
+                let state = ProcessState::Crashed {
+                    exit_code,
+                    signal,
+                    crashes,
+                    restart_in_secs: backoff,
+                };
+                supervisor.set_state(state, &*events).await;
+                tokio::select! {
+                    _ = sleep(Duration::from_secs(backoff)) => {}
+                    _ = wake.notified() => {}
+                }
+
+                if supervisor.stopping.load(Ordering::SeqCst) {
+                    supervisor.set_state(ProcessState::Stopped, &*events).await;
+                    return;
+                }
+
+                info!("Restarting {}", supervisor.component);
+                match spawn() {
+                    Ok((new_rx, child)) => {
+                        rx = new_rx;
+                        started = Instant::now();
+                        supervisor.set_running(child, &*events).await;
+                    }
+                    Err(e) => {
+                        error!("Failed to restart {}: {}", supervisor.component, e);
+                        let state = ProcessState::Failed {
+                            exit_code,
+                            signal,
+                            crashes,
+                        };
+                        supervisor.set_state(state, &*events).await;
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn set_running(&self, child: CommandChild, events: &dyn MinistoEvents) {
+        let pid = child.pid();
+        *self.child.lock().await = Some(child);
+        self.set_state(ProcessState::Running { pid }, events).await;
+    }
+
+    async fn set_state(&self, state: ProcessState, events: &dyn MinistoEvents) {
+        *self.state.lock().await = state.clone();
+        events.publish(&Event::ProcessState {
+            component: self.component,
+            state,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tauri::api::process::Command;
+
+    use super::*;
+    use crate::events::MemoryEvents;
+
+    fn config(max_restarts: u32) -> SupervisorConfig {
+        SupervisorConfig {
+            max_restarts,
+            initial_backoff_secs: 0,
+            max_backoff_secs: 0,
+        }
+    }
+
+    fn states(events: &MemoryEvents) -> Vec<ProcessState> {
+        events
+            .events()
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::ProcessState { state, .. } => Some(state),
+                _ => None,
+            })
+            .collect()
+    }
+
+    async fn wait_until_inactive(supervisor: &Supervisor) {
+        for _ in 0..100 {
+            if !supervisor.is_active().await {
+                return;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+        panic!("process is still active");
+    }
+
+    #[tokio::test]
+    async fn restarts_until_crash_budget_is_spent() {
+        let supervisor = Arc::new(Supervisor::new(Component::P2pool));
+        let events = Arc::new(MemoryEvents::default());
+        supervisor
+            .supervise(
+                config(2),
+                events.clone(),
+                || Ok(Command::new("sh").args(["-c", "exit 3"]).spawn()?),
+                |_| {},
+            )
+            .await
+            .unwrap();
+        wait_until_inactive(&supervisor).await;
+
+        let states = states(&events);
+        assert_eq!(states.len(), 6, "unexpected states: {:?}", states);
+        assert!(matches!(
+            states[1],
+            ProcessState::Crashed {
+                exit_code: Some(3),
+                crashes: 1,
+                ..
+            }
+        ));
+        assert!(matches!(states[4], ProcessState::Running { .. }));
+        assert!(matches!(
+            states[5],
+            ProcessState::Failed {
+                exit_code: Some(3),
+                crashes: 3,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn stop_cancels_pending_restart() {
+        let supervisor = Arc::new(Supervisor::new(Component::P2pool));
+        let events = Arc::new(MemoryEvents::default());
+        let slow = SupervisorConfig {
+            max_restarts: 2,
+            initial_backoff_secs: 60,
+            max_backoff_secs: 60,
+        };
+        supervisor
+            .supervise(
+                slow,
+                events.clone(),
+                || Ok(Command::new("sh").args(["-c", "exit 3"]).spawn()?),
+                |_| {},
+            )
+            .await
+            .unwrap();
+        while !matches!(*supervisor.state.lock().await, ProcessState::Crashed { .. }) {
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        let result = supervisor
+            .stop(async { Ok(()) }, Duration::from_millis(500))
+            .await;
+        assert_eq!(result, StopResult::NotRunning);
+        assert!(!supervisor.is_active().await);
+        assert_eq!(states(&events).last(), Some(&ProcessState::Stopped));
+    }
+
+    #[tokio::test]
+    async fn clean_exit_is_not_restarted() {
+        let supervisor = Arc::new(Supervisor::new(Component::Monerod));
+        let events = Arc::new(MemoryEvents::default());
+        supervisor
+            .supervise(
+                config(2),
+                events.clone(),
+                || Ok(Command::new("sh").args(["-c", "exit 0"]).spawn()?),
+                |_| {},
+            )
+            .await
+            .unwrap();
+        wait_until_inactive(&supervisor).await;
+
+        let states = states(&events);
+        assert_eq!(states.len(), 2, "unexpected states: {:?}", states);
+        assert_eq!(states[1], ProcessState::Stopped);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn stop_escalates_to_sigterm() {
+        let supervisor = Arc::new(Supervisor::new(Component::Xmrig));
+        let events = Arc::new(MemoryEvents::default());
+        supervisor
+            .supervise(
+                config(2),
+                events.clone(),
+                || Ok(Command::new("sleep").args(["30"]).spawn()?),
+                |_| {},
+            )
+            .await
+            .unwrap();
+
+        // Ignore the request to exit, so that the supervisor has to escalate.
+        let result = supervisor
+            .stop(async { Ok(()) }, Duration::from_millis(500))
+            .await;
+        assert_eq!(result, StopResult::Terminated);
+        wait_until_inactive(&supervisor).await;
+        assert_eq!(states(&events).last(), Some(&ProcessState::Stopped));
+    }
+}
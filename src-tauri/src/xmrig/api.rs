@@ -0,0 +1,409 @@
+use std::{error, fmt};
+
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tokio::sync::Mutex;
+
+use crate::config::port;
+
+/// Client for XMRig's HTTP API.
+#[derive(Debug)]
+pub struct XmrigApi {
+    client: reqwest::Client,
+    bearer_token: Mutex<String>,
+    port: Mutex<u16>,
+}
+
+impl XmrigApi {
+    pub fn new() -> XmrigApi {
+        XmrigApi {
+            client: reqwest::Client::new(),
+            bearer_token: Mutex::new(String::default()),
+            port: Mutex::new(port::XMRIG_API),
+        }
+    }
+
+    /// Set the port XMRig's API was started on.
+    pub async fn set_port(&self, port: u16) {
+        *self.port.lock().await = port;
+    }
+
+    /// Set the access token XMRig was started with.
+    pub async fn set_bearer_token(&self, token: String) {
+        *self.bearer_token.lock().await = token;
+    }
+
+    pub async fn bearer_token(&self) -> String {
+        self.bearer_token.lock().await.clone()
+    }
+
+    pub async fn summary(&self) -> Result<Summary, ApiError> {
+        self.get("/2/summary").await
+    }
+
+    pub async fn backends(&self) -> Result<Vec<Backend>, ApiError> {
+        self.get("/2/backends").await
+    }
+
+    pub async fn config(&self) -> Result<ApiConfig, ApiError> {
+        self.get("/1/config").await
+    }
+
+    /// Replace XMRig's whole configuration, which it applies without restarting.
+    pub async fn set_config(&self, config: &Value) -> Result<(), ApiError> {
+        let request = self.request(Method::PUT, "/1/config").await.json(config);
+        self.check(request).await?;
+        Ok(())
+    }
+
+    /// Pause mining.
+    pub async fn pause(&self) -> Result<(), ApiError> {
+        self.json_rpc("pause").await
+    }
+
+    /// Resume mining after a pause.
+    pub async fn resume(&self) -> Result<(), ApiError> {
+        self.json_rpc("resume").await
+    }
+
+    /// Stop mining. Unlike pausing, this also releases the memory used for mining.
+    pub async fn stop(&self) -> Result<(), ApiError> {
+        self.json_rpc("stop").await
+    }
+
+    async fn json_rpc(&self, method: &str) -> Result<(), ApiError> {
+        let request = self
+            .request(Method::POST, "/json_rpc")
+            .await
+            .json(&json!({"method": method, "id": 1}));
+        let response: JsonRpcResponse = self.send(request).await?;
+        match response.error {
+            Some(e) => Err(ApiError::Rpc {
+                code: e.code,
+                message: e.message,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ApiError> {
+        let request = self.request(Method::GET, path).await;
+        self.send(request).await
+    }
+
+    async fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let token = self.bearer_token.lock().await;
+        let port = *self.port.lock().await;
+        self.client
+            .request(method, format!("http://127.0.0.1:{}{}", port, path))
+            .bearer_auth(&*token)
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ApiError> {
+        let response = self.check(request).await?;
+        let body = response.text().await.map_err(ApiError::Http)?;
+        serde_json::from_str(&body).map_err(ApiError::Parse)
+    }
+
+    /// Send `request`, turning error statuses into errors.
+    async fn check(&self, request: RequestBuilder) -> Result<Response, ApiError> {
+        let response = request.send().await.map_err(ApiError::Http)?;
+        match response.status() {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(ApiError::Unauthorized),
+            status if !status.is_success() => Err(ApiError::Status(status)),
+            _ => Ok(response),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+    /// XMRig could not be reached.
+    Http(reqwest::Error),
+    /// XMRig rejected our access token.
+    Unauthorized,
+    /// XMRig responded with an unexpected HTTP status.
+    Status(StatusCode),
+    /// XMRig's response could not be parsed.
+    Parse(serde_json::Error),
+    /// XMRig responded to a json_rpc call with an error.
+    Rpc { code: i64, message: String },
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Http(e) => write!(f, "failed to reach XMRig: {}", e),
+            ApiError::Unauthorized => write!(f, "XMRig rejected the access token"),
+            ApiError::Status(status) => write!(f, "XMRig responded with {}", status),
+            ApiError::Parse(e) => write!(f, "failed to parse XMRig response: {}", e),
+            ApiError::Rpc { code, message } => {
+                write!(f, "XMRig responded with error {}: {}", code, message)
+            }
+        }
+    }
+}
+
+impl error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ApiError::Http(e) => Some(e),
+            ApiError::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonRpcResponse {
+    error: Option<JsonRpcError>,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcError {
+    code: i64,
+    message: String,
+}
+
+/// Response from XMRig's `/2/summary` endpoint.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Summary {
+    pub id: String,
+    pub worker_id: String,
+    pub uptime: u64,
+    pub version: String,
+    pub algo: Option<String>,
+    pub paused: bool,
+    pub donate_level: u32,
+    pub hashrate: Hashrate,
+    pub results: Results,
+    pub connection: Connection,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Hashrate {
+    /// Total hashrate over the last 10 seconds, 60 seconds, and 15 minutes.
+    pub total: Vec<Option<f64>>,
+    pub highest: Option<f64>,
+    /// Hashrate of each thread, over the same periods as `total`.
+    pub threads: Vec<Vec<Option<f64>>>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Results {
+    pub diff_current: u64,
+    pub shares_good: u64,
+    pub shares_total: u64,
+    pub avg_time: u64,
+    pub hashes_total: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Connection {
+    pub pool: String,
+    pub ip: Option<String>,
+    pub uptime: u64,
+    pub ping: u64,
+    pub failures: u64,
+    pub tls: Option<String>,
+    pub algo: Option<String>,
+    pub diff: u64,
+    pub accepted: u64,
+    pub rejected: u64,
+}
+
+/// An entry in the response from XMRig's `/2/backends` endpoint.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Backend {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub enabled: bool,
+    pub algo: Option<String>,
+    pub profile: Option<String>,
+    pub msr: Option<bool>,
+    /// Hashrate over the last 10 seconds, 60 seconds, and 15 minutes.
+    pub hashrate: Vec<Option<f64>>,
+    pub threads: Vec<BackendThread>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct BackendThread {
+    pub intensity: u32,
+    pub affinity: i64,
+    pub hashrate: Vec<Option<f64>>,
+}
+
+/// XMRig's configuration, as returned by its `/1/config` endpoint. Settings Ministo doesn't use
+/// are kept in `other`, so that the configuration can be sent back unchanged.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ApiConfig {
+    #[serde(rename = "donate-level")]
+    pub donate_level: u32,
+    pub cpu: CpuConfig,
+    pub pools: Vec<PoolConfig>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CpuConfig {
+    pub enabled: bool,
+    #[serde(rename = "huge-pages")]
+    pub huge_pages: bool,
+    pub priority: Option<i32>,
+    #[serde(rename = "max-threads-hint")]
+    pub max_threads_hint: u32,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PoolConfig {
+    pub url: Option<String>,
+    pub user: Option<String>,
+    pub pass: Option<String>,
+    #[serde(rename = "rig-id")]
+    pub rig_id: Option<String>,
+    pub tls: bool,
+    pub coin: Option<String>,
+    pub algo: Option<String>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        task::JoinHandle,
+    };
+
+    /// Serve each of `responses` to one connection, in order, returning an API client pointed at
+    /// the server and the requests it received.
+    async fn mock_server(responses: Vec<String>) -> (XmrigApi, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api = XmrigApi::new();
+        api.set_port(listener.local_addr().unwrap().port()).await;
+        api.set_bearer_token("secret".to_string()).await;
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                requests.push(read_request(&mut stream).await);
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.shutdown().await.unwrap();
+            }
+            requests
+        });
+        (api, server)
+    }
+
+    async fn read_request(stream: &mut tokio::net::TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some(end) = text.find("\r\n\r\n") {
+                let content_length = text[..end]
+                    .lines()
+                    .filter_map(|line| line.split_once(':'))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                    .map(|(_, value)| value.trim().parse::<usize>().unwrap())
+                    .unwrap_or(0);
+                if request.len() >= end + 4 + content_length || n == 0 {
+                    return text.into_owned();
+                }
+            }
+        }
+    }
+
+    fn response(status: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
+    #[tokio::test]
+    async fn summary() {
+        let body = json!({
+            "worker_id": "rig",
+            "paused": true,
+            "hashrate": {"total": [1000.5, null, null], "highest": 1200.0},
+            "connection": {"pool": "127.0.0.1:3333", "accepted": 4},
+        });
+        let (api, server) = mock_server(vec![response("200 OK", &body.to_string())]).await;
+
+        let summary = api.summary().await.unwrap();
+        assert!(summary.paused);
+        assert_eq!(summary.worker_id, "rig");
+        assert_eq!(summary.hashrate.total, [Some(1000.5), None, None]);
+        assert_eq!(summary.connection.accepted, 4);
+
+        let request = server.await.unwrap().remove(0);
+        assert!(request.starts_with("GET /2/summary "), "{}", request);
+        assert!(
+            request
+                .to_lowercase()
+                .contains("authorization: bearer secret"),
+            "{}",
+            request
+        );
+    }
+
+    #[tokio::test]
+    async fn rejected_token() {
+        let (api, _server) = mock_server(vec![response("401 Unauthorized", "")]).await;
+        assert!(matches!(api.summary().await, Err(ApiError::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn json_rpc() {
+        let ok = response(
+            "200 OK",
+            r#"{"id": 1, "jsonrpc": "2.0", "result": {"status": "OK"}}"#,
+        );
+        let (api, server) = mock_server(vec![ok.clone(), ok.clone(), ok]).await;
+        api.pause().await.unwrap();
+        api.resume().await.unwrap();
+        api.stop().await.unwrap();
+
+        let requests = server.await.unwrap();
+        for (request, method) in requests.iter().zip(["pause", "resume", "stop"]) {
+            assert!(request.starts_with("POST /json_rpc "), "{}", request);
+            let body = &request[request.find("\r\n\r\n").unwrap() + 4..];
+            let body: Value = serde_json::from_str(body).unwrap();
+            assert_eq!(body["method"], method);
+        }
+    }
+
+    #[tokio::test]
+    async fn json_rpc_error() {
+        let error = response(
+            "200 OK",
+            r#"{"id": 1, "jsonrpc": "2.0", "error": {"code": -32601, "message": "Method not found"}}"#,
+        );
+        let (api, _server) = mock_server(vec![error]).await;
+        match api.pause().await {
+            Err(ApiError::Rpc { code, message }) => {
+                assert_eq!(code, -32601);
+                assert_eq!(message, "Method not found");
+            }
+            other => panic!("expected an RPC error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! XMRig's `config.json`, rendered from the active profile. XMRig is started with it, and is sent
+//! it through the API when settings change while it's running.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use anyhow::{Error, Result};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    config::{
+        pool::{Pool, RemotePool},
+        xmrig::{XmrigConfig, XmrigPrivileges},
+        Profile,
+    },
+    ports::Ports,
+};
+
+/// XMRig's configuration for mining with `profile`, serving its API on the port in `ports` with
+/// the access token `token`.
+pub fn render(profile: &Profile, ports: &Ports, token: &str) -> Result<Value> {
+    let xmrig = &profile.xmrig;
+    let mut pools = vec![match &profile.pool {
+        Pool::Local(_) => json!({
+            "url": format!("127.0.0.1:{}", ports.p2pool_stratum),
+            "coin": "monero",
+        }),
+        Pool::Remote(pool) => {
+            let settings = remote_pool(pool)?;
+            if pool.user.is_none() {
+                return Err(Error::msg(
+                    "A wallet address or username is required to mine on a remote pool",
+                ));
+            }
+            settings
+        }
+    }];
+    // XMRig tries the pools in order, so these are only used while the ones before them are down.
+    for pool in &profile.failover_pools {
+        pools.push(remote_pool(pool)?);
+    }
+
+    let mut config = json!({
+        // XMRig would otherwise write its own defaults into the file, as root if it runs as root.
+        "autosave": false,
+        "http": {
+            "enabled": true,
+            "host": "127.0.0.1",
+            "port": ports.xmrig_api,
+            "access-token": token,
+            "restricted": false,
+        },
+        "cpu": cpu(xmrig),
+        "randomx": randomx(xmrig),
+        "pools": pools,
+        "verbose": u8::from(xmrig.verbose),
+    });
+    if let Some(level) = xmrig.donate_level {
+        config["donate-level"] = level.into();
+    }
+    Ok(config)
+}
+
+fn cpu(xmrig: &XmrigConfig) -> Value {
+    let mut cpu = json!({
+        "enabled": true,
+        "huge-pages": xmrig.huge_pages,
+        "priority": xmrig.cpu_priority,
+        "yield": xmrig.cpu_yield,
+        "max-threads-hint": xmrig.max_threads_hint.unwrap_or(100),
+    });
+    // Without a thread count, an affinity gets one thread per CPU in it.
+    let threads = xmrig.threads.or(xmrig.cpu_affinity.map(u64::count_ones));
+    if let Some(threads) = threads {
+        // Applies to every algorithm, rather than the threads XMRig would choose.
+        cpu["*"] = json!({
+            "intensity": 1,
+            "threads": threads,
+            "affinity": xmrig.cpu_affinity.map_or(-1, |mask| mask as i64),
+        });
+    }
+    cpu
+}
+
+fn randomx(xmrig: &XmrigConfig) -> Value {
+    // Running unprivileged, XMRig can't write them. `ministo --tune-system` does instead.
+    let unprivileged = cfg!(unix) && xmrig.privileges == XmrigPrivileges::Unprivileged;
+    json!({
+        "init": xmrig.randomx_init.map_or(-1, i64::from),
+        "mode": xmrig.randomx_mode.to_string(),
+        "1gb-pages": xmrig.one_gb_pages,
+        "wrmsr": xmrig.msr_mod && !unprivileged,
+        "cache_qos": xmrig.cache_qos,
+    })
+}
+
+/// XMRig's settings for mining on a remote pool. A P2Pool node elsewhere doesn't need a user.
+fn remote_pool(pool: &RemotePool) -> Result<Value> {
+    if pool.ip.is_empty() {
+        return Err(Error::msg("Remote pool address not configured"));
+    }
+
+    let mut settings = Map::new();
+    settings.insert("url".into(), format!("{}:{}", pool.ip, pool.port).into());
+    settings.insert("tls".into(), pool.tls.into());
+    for (key, value) in [
+        ("user", &pool.user),
+        ("pass", &pool.password),
+        ("rig-id", &pool.rig_id),
+        ("algo", &pool.algorithm),
+        ("coin", &pool.coin),
+    ] {
+        if let Some(value) = value {
+            settings.insert(key.into(), value.as_str().into());
+        }
+    }
+    Ok(Value::Object(settings))
+}
+
+/// The URLs of the pools in `config`, in the order XMRig tries them.
+pub fn pool_urls(config: &Value) -> Vec<String> {
+    config["pools"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|pool| pool["url"].as_str().map(str::to_string))
+        .collect()
+}
+
+/// Write `config` to `path`, readable only by us as it holds the API's access token.
+pub fn write(path: &Path, config: &Value) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    file.write_all(serde_json::to_string_pretty(config)?.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::xmrig::RandomxMode;
+
+    use tempfile::tempdir;
+
+    #[test]
+    fn local_pool() {
+        let ports = Ports {
+            p2pool_stratum: 3335,
+            xmrig_api: 3336,
+            ..Ports::default()
+        };
+        let config = render(&Profile::default(), &ports, "token").unwrap();
+        assert_eq!(
+            config["pools"],
+            json!([{"url": "127.0.0.1:3335", "coin": "monero"}])
+        );
+        assert_eq!(config["http"]["port"], 3336);
+        assert_eq!(config["http"]["access-token"], "token");
+        assert_eq!(config["randomx"]["wrmsr"], true);
+        assert_eq!(config["cpu"]["max-threads-hint"], 100);
+        assert!(config["cpu"].get("*").is_none());
+        assert!(config.get("donate-level").is_none());
+    }
+
+    #[test]
+    fn remote_pool_and_settings() {
+        let profile = Profile {
+            pool: Pool::Remote(RemotePool {
+                ip: "pool.example.com".to_string(),
+                port: 443,
+                user: Some("4abc".to_string()),
+                password: None,
+                tls: true,
+                rig_id: Some("rig".to_string()),
+                algorithm: None,
+                coin: None,
+            }),
+            xmrig: XmrigConfig {
+                cpu_affinity: Some(0b1010),
+                cpu_priority: Some(1),
+                randomx_mode: RandomxMode::Light,
+                msr_mod: false,
+                donate_level: Some(0),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let config = render(&profile, &Ports::default(), "token").unwrap();
+        assert_eq!(
+            config["pools"],
+            json!([{"url": "pool.example.com:443", "user": "4abc", "tls": true, "rig-id": "rig"}])
+        );
+        assert_eq!(
+            config["cpu"]["*"],
+            json!({"intensity": 1, "threads": 2, "affinity": 10})
+        );
+        assert_eq!(config["cpu"]["priority"], 1);
+        assert_eq!(config["randomx"]["mode"], "light");
+        assert_eq!(config["randomx"]["wrmsr"], false);
+        assert_eq!(config["donate-level"], 0);
+    }
+
+    #[test]
+    fn failover_pools_follow_the_profiles_pool() {
+        let lan_node = RemotePool {
+            ip: "192.168.1.20".to_string(),
+            port: 3333,
+            user: None,
+            password: None,
+            tls: false,
+            rig_id: None,
+            algorithm: None,
+            coin: None,
+        };
+        let remote = RemotePool {
+            ip: "pool.example.com".to_string(),
+            port: 443,
+            user: Some("4abc".to_string()),
+            tls: true,
+            ..lan_node.clone()
+        };
+        let profile = Profile {
+            failover_pools: vec![lan_node, remote],
+            ..Default::default()
+        };
+        let config = render(&profile, &Ports::default(), "token").unwrap();
+        assert_eq!(
+            pool_urls(&config),
+            [
+                "127.0.0.1:3333",
+                "192.168.1.20:3333",
+                "pool.example.com:443"
+            ]
+        );
+        assert!(config["pools"][1].get("user").is_none());
+        assert_eq!(config["pools"][2]["user"], "4abc");
+    }
+
+    #[test]
+    fn remote_pool_needs_a_user() {
+        let profile = Profile {
+            pool: Pool::Remote(RemotePool {
+                ip: "pool.example.com".to_string(),
+                port: 443,
+                user: None,
+                password: None,
+                tls: true,
+                rig_id: None,
+                algorithm: None,
+                coin: None,
+            }),
+            ..Default::default()
+        };
+        assert!(render(&profile, &Ports::default(), "token").is_err());
+    }
+
+    #[test]
+    fn writes_private_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("xmrig").join("config.json");
+        write(&path, &json!({"autosave": false})).unwrap();
+        let written: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written, json!({"autosave": false}));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
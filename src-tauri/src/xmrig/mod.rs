@@ -0,0 +1,422 @@
+pub mod api;
+pub mod config_file;
+pub mod tuning;
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex as StdMutex},
+    time::Duration,
+};
+
+use anyhow::{Error, Result};
+use log::{debug, info, warn};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Serialize;
+use tauri::{api::process::Command, command, utils::platform::current_exe, State};
+use tokio::time::interval;
+
+use self::api::{ApiConfig, Backend, XmrigApi};
+use crate::{
+    config::{
+        xmrig::{XmrigConfig, XmrigPrivileges},
+        Profile, SupervisorConfig,
+    },
+    events::{Event, MinistoEvents},
+    supervisor::{Component, StopResult, Supervisor},
+    MinistoState,
+};
+
+pub async fn start_xmrig(state: &MinistoState) -> Result<()> {
+    let config = &state.config.lock().await;
+    config.profile().xmrig.validate()?;
+
+    // If a token was supplied in config, use it. Otherwise, generate one.
+    let token = match &config.profile().xmrig.bearer_token {
+        Some(t) => t.to_owned(),
+        None => {
+            // Generate a 32 character bearer token.
+            rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(32)
+                .map(char::from)
+                .collect()
+        }
+    };
+    state.xmrig.api.set_bearer_token(token.clone()).await;
+
+    let ports = *state.ports.lock().expect("ports lock poisoned");
+    state.xmrig.api.set_port(ports.xmrig_api).await;
+
+    let config_file = state.xmrig.config_file();
+    let profile = state.xmrig.throttled(config.profile());
+    let rendered = config_file::render(&profile, &ports, &token)?;
+    config_file::write(&config_file, &rendered)?;
+    state.xmrig.set_pools(config_file::pool_urls(&rendered));
+    let config_arg = format!("--config={}", config_file.display());
+    let args = vec![config_arg.as_str()];
+
+    let privileges = config.profile().xmrig.privileges;
+    *state
+        .xmrig
+        .privileges
+        .lock()
+        .expect("xmrig privileges lock poisoned") = privileges;
+
+    #[cfg(unix)]
+    let xmrig_path = match current_exe()?.parent() {
+        Some(exec_dir) => format!("{}/{}", exec_dir.display(), "xmrig"),
+        None => return Err(Error::msg("Failed to determine xmrig directory.")),
+    };
+    #[cfg(unix)]
+    let args: Vec<String> = std::iter::once(xmrig_path.as_str())
+        .chain(args)
+        .map(String::from)
+        .collect();
+    #[cfg(unix)]
+    let spawn = move || -> Result<_> {
+        Ok(match privileges {
+            XmrigPrivileges::Root => Command::new("pkexec").args(&args).spawn()?,
+            XmrigPrivileges::Unprivileged => Command::new(&args[0]).args(&args[1..]).spawn()?,
+        })
+    };
+
+    #[cfg(not(unix))]
+    let args: Vec<String> = args.into_iter().map(String::from).collect();
+    #[cfg(not(unix))]
+    let spawn = move || -> Result<_> { Ok(Command::new_sidecar("xmrig")?.args(&args).spawn()?) };
+
+    let events: Arc<dyn MinistoEvents> = state.events.clone();
+    let output_events = events.clone();
+    state
+        .xmrig
+        .process
+        .supervise(
+            supervisor_config(config.supervisor, privileges),
+            events.clone(),
+            spawn,
+            move |line| {
+                debug!("{}", line);
+                output_events.publish(&Event::Output {
+                    component: Component::Xmrig,
+                    line,
+                });
+            },
+        )
+        .await?;
+
+    let xmrig_state = state.xmrig.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = interval(Duration::from_secs(8));
+        let mut active_pool = String::new();
+        loop {
+            interval.tick().await;
+            if !xmrig_state.process.is_active().await {
+                break;
+            }
+            // Get xmrig status.
+            match xmrig_state.api.summary().await {
+                Ok(status) => {
+                    let url = &status.connection.pool;
+                    if !url.is_empty() && *url != active_pool {
+                        active_pool = url.clone();
+                        let pool = ActivePool::new(&xmrig_state.pools(), url);
+                        if pool.failover {
+                            warn!("XMRig failed over to {}", url);
+                        } else {
+                            info!("XMRig is mining on {}", url);
+                        }
+                        events.publish(&Event::ActivePool(pool));
+                    }
+                    // Send status event.
+                    events.publish(&Event::XmrigStatus(Box::new(status)));
+                }
+                Err(e) => {
+                    warn!("No response from XMRig: {}", e);
+                }
+            };
+        }
+    });
+    Ok(())
+}
+
+/// The pool XMRig mines on, out of the profile's pool and its failover pools.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivePool {
+    pub url: String,
+    /// Where the pool is in the order XMRig tries them: 0 for the profile's pool, 1 for the first
+    /// failover pool, and so on. `None` if it's none of them.
+    pub index: Option<usize>,
+    /// Whether XMRig failed over from the profile's pool.
+    pub failover: bool,
+}
+
+impl ActivePool {
+    fn new(pools: &[String], url: &str) -> ActivePool {
+        let index = pools.iter().position(|pool| pool == url);
+        ActivePool {
+            url: url.to_string(),
+            index,
+            failover: index != Some(0),
+        }
+    }
+}
+
+/// Apply the active profile's settings to the running XMRig through its API, without restarting
+/// it.
+pub async fn reconfigure_xmrig(state: &MinistoState) -> Result<()> {
+    let config = state.config.lock().await;
+    config.profile().xmrig.validate()?;
+    let ports = *state.ports.lock().expect("ports lock poisoned");
+    let token = state.xmrig.api.bearer_token().await;
+    let profile = state.xmrig.throttled(config.profile());
+    let rendered = config_file::render(&profile, &ports, &token)?;
+    // Keep the file up to date too, for when XMRig restarts after a crash.
+    config_file::write(&state.xmrig.config_file(), &rendered)?;
+    state.xmrig.api.set_config(&rendered).await?;
+    state.xmrig.set_pools(config_file::pool_urls(&rendered));
+    info!("Reconfigured XMRig");
+    Ok(())
+}
+
+/// How XMRig is supervised. XMRig running as root isn't restarted after a crash, as `pkexec` would
+/// ask for the user's password again each time.
+fn supervisor_config(config: SupervisorConfig, privileges: XmrigPrivileges) -> SupervisorConfig {
+    match privileges {
+        #[cfg(unix)]
+        XmrigPrivileges::Root => SupervisorConfig {
+            max_restarts: 0,
+            ..config
+        },
+        _ => config,
+    }
+}
+
+/// Whether XMRig has to be restarted to go from the settings `old` to `new`, rather than being
+/// reconfigured through its API.
+pub fn needs_restart(old: &XmrigConfig, new: &XmrigConfig) -> bool {
+    old.privileges != new.privileges
+        || old.api_port != new.api_port
+        || old.bearer_token != new.bearer_token
+}
+
+/// Stop XMRig, asking it to quit before resorting to signals. XMRig running as root can only be
+/// signalled as root, through `pkexec`, so that's left for when it doesn't quit.
+pub async fn stop_xmrig(state: &XmrigState) -> StopResult {
+    let privileges = *state
+        .privileges
+        .lock()
+        .expect("xmrig privileges lock poisoned");
+    let timeout = Duration::from_secs(10);
+    let request_exit = async {
+        // Stop hashing right away, even if XMRig takes a while to quit.
+        if let Err(e) = state.api.stop().await {
+            debug!("Failed to stop XMRig's miner: {}", e);
+        }
+        match privileges {
+            #[cfg(unix)]
+            XmrigPrivileges::Unprivileged => state.process.terminate().await,
+            // We can't signal XMRig running as root, but it reads commands from its console.
+            _ => state.process.write(b"q").await,
+        }
+    };
+    let result = state.process.stop(request_exit, timeout).await;
+
+    #[cfg(unix)]
+    if let (XmrigPrivileges::Root, StopResult::Failed { error }) = (privileges, &result) {
+        // This asks for the user's password again.
+        warn!("{}; stopping XMRig through pkexec", error);
+        return match terminate_as_root(&state.process).await {
+            Ok(()) if state.process.wait_for_exit(timeout).await => StopResult::Terminated,
+            Ok(()) => StopResult::Failed {
+                error: "XMRig did not exit".to_string(),
+            },
+            Err(e) => StopResult::Failed {
+                error: e.to_string(),
+            },
+        };
+    }
+    result
+}
+
+/// Send XMRig running as root SIGTERM through `pkexec`.
+#[cfg(unix)]
+async fn terminate_as_root(process: &Supervisor) -> Result<()> {
+    let pid = match process.pid().await {
+        Some(pid) => pid.to_string(),
+        None => return Ok(()),
+    };
+    let status = tauri::async_runtime::spawn_blocking(move || {
+        std::process::Command::new("pkexec")
+            .args(["kill", "-TERM", &pid])
+            .status()
+    })
+    .await??;
+    if !status.success() {
+        return Err(Error::msg(format!(
+            "pkexec failed to stop XMRig ({})",
+            status
+        )));
+    }
+    Ok(())
+}
+
+#[command(async)]
+pub async fn pause_mining(state: State<'_, MinistoState>) -> Result<(), String> {
+    let res = state.xmrig.api.pause().await;
+    debug!("XMRig pause command result: {:?}", res);
+    if res.is_ok() {
+        state
+            .schedule
+            .override_until_next_transition(&*state.events);
+    }
+    res.map_err(|e| e.to_string())
+}
+
+#[command(async)]
+pub async fn resume_mining(state: State<'_, MinistoState>) -> Result<(), String> {
+    let res = state.xmrig.api.resume().await;
+    debug!("XMRig resume command result: {:?}", res);
+    if res.is_ok() {
+        state
+            .schedule
+            .override_until_next_transition(&*state.events);
+    }
+    res.map_err(|e| e.to_string())
+}
+
+#[command(async)]
+pub async fn xmrig_backends(state: State<'_, MinistoState>) -> Result<Vec<Backend>, String> {
+    state.xmrig.api.backends().await.map_err(|e| e.to_string())
+}
+
+#[command(async)]
+pub async fn xmrig_config(state: State<'_, MinistoState>) -> Result<ApiConfig, String> {
+    state.xmrig.api.config().await.map_err(|e| e.to_string())
+}
+
+#[derive(Debug)]
+pub struct XmrigState {
+    pub api: XmrigApi,
+    pub process: Arc<Supervisor>,
+    /// What XMRig was last started with.
+    privileges: StdMutex<XmrigPrivileges>,
+    /// URLs of the pools XMRig was configured with, in the order it tries them.
+    pools: StdMutex<Vec<String>>,
+    /// Threads XMRig is held to while the user is active, if any.
+    active_threads: StdMutex<Option<u32>>,
+    /// Where XMRig's `config.json` is written.
+    dir: PathBuf,
+}
+
+impl XmrigState {
+    pub fn new(dir: PathBuf) -> XmrigState {
+        XmrigState {
+            api: XmrigApi::new(),
+            process: Arc::new(Supervisor::new(Component::Xmrig)),
+            privileges: StdMutex::new(XmrigPrivileges::default()),
+            pools: StdMutex::new(Vec::new()),
+            active_threads: StdMutex::new(None),
+            dir,
+        }
+    }
+
+    fn pools(&self) -> Vec<String> {
+        self.pools
+            .lock()
+            .expect("xmrig pools lock poisoned")
+            .clone()
+    }
+
+    fn set_pools(&self, pools: Vec<String>) {
+        *self.pools.lock().expect("xmrig pools lock poisoned") = pools;
+    }
+
+    pub fn active_threads(&self) -> Option<u32> {
+        *self
+            .active_threads
+            .lock()
+            .expect("xmrig active threads lock poisoned")
+    }
+
+    pub fn set_active_threads(&self, threads: Option<u32>) {
+        *self
+            .active_threads
+            .lock()
+            .expect("xmrig active threads lock poisoned") = threads;
+    }
+
+    /// `profile`, with XMRig held to fewer threads while the user is active.
+    fn throttled(&self, profile: &Profile) -> Profile {
+        let mut profile = profile.clone();
+        if let Some(threads) = self.active_threads() {
+            profile.xmrig.threads = Some(threads);
+            profile.xmrig.max_threads_hint = None;
+        }
+        profile
+    }
+
+    pub fn config_file(&self) -> PathBuf {
+        self.dir.join("config.json")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::port::Port;
+
+    #[test]
+    fn restarts_only_for_api_and_privileges() {
+        let old = XmrigConfig::default();
+        let faster = XmrigConfig {
+            threads: Some(8),
+            cache_qos: true,
+            ..Default::default()
+        };
+        assert!(!needs_restart(&old, &faster));
+        let moved_api = XmrigConfig {
+            api_port: Port::Fixed(4000),
+            ..Default::default()
+        };
+        assert!(needs_restart(&old, &moved_api));
+        let unprivileged = XmrigConfig {
+            privileges: XmrigPrivileges::Unprivileged,
+            ..Default::default()
+        };
+        assert!(needs_restart(&old, &unprivileged));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn root_is_not_restarted() {
+        let config = SupervisorConfig::default();
+        assert_eq!(
+            supervisor_config(config, XmrigPrivileges::Root).max_restarts,
+            0
+        );
+        assert_eq!(
+            supervisor_config(config, XmrigPrivileges::Unprivileged).max_restarts,
+            config.max_restarts
+        );
+    }
+
+    #[test]
+    fn active_pools() {
+        let pools = [
+            "127.0.0.1:3333".to_string(),
+            "192.168.1.20:3333".to_string(),
+        ];
+        assert!(!ActivePool::new(&pools, "127.0.0.1:3333").failover);
+        assert_eq!(
+            ActivePool::new(&pools, "192.168.1.20:3333"),
+            ActivePool {
+                url: "192.168.1.20:3333".to_string(),
+                index: Some(1),
+                failover: true,
+            }
+        );
+        assert_eq!(ActivePool::new(&pools, "pool.example.com:443").index, None);
+    }
+}
@@ -0,0 +1,238 @@
+//! Setting the system up for XMRig once, as root, so that XMRig itself can run unprivileged.
+
+#[cfg(target_os = "linux")]
+use std::{
+    fs::{self, OpenOptions},
+    os::unix::fs::FileExt,
+    path::Path,
+    process,
+};
+
+use anyhow::{Error, Result};
+use tauri::{
+    api::process::{Command, CommandEvent},
+    command,
+    utils::platform::current_exe,
+};
+
+/// Huge pages XMRig uses for RandomX's dataset and a few mining threads, in bytes.
+#[cfg(target_os = "linux")]
+pub const HUGE_PAGES_WANTED: u64 = 1280 * 2 * 1024 * 1024;
+/// Left behind once the model-specific registers are tuned. `/run` is emptied on reboot, as are
+/// the registers.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+pub const MSR_TUNED_MARKER: &str = "/run/ministo-msr-tuned";
+
+/// Register writes of one of XMRig's RandomX presets: register, value, and which bits of the value
+/// to write.
+#[cfg(any(target_os = "linux", test))]
+type MsrPreset = &'static [(u32, u64, u64)];
+
+#[cfg(any(target_os = "linux", test))]
+const INTEL: MsrPreset = &[(0x1a4, 0xf, u64::MAX)];
+#[cfg(any(target_os = "linux", test))]
+const RYZEN_17H: MsrPreset = &[
+    (0xc001_1020, 0x0, u64::MAX),
+    (0xc001_1021, 0x40, !0x20),
+    (0xc001_1022, 0x0151_0000, u64::MAX),
+    (0xc001_102b, 0x2000_cc16, u64::MAX),
+];
+#[cfg(any(target_os = "linux", test))]
+const RYZEN_19H: MsrPreset = &[
+    (0xc001_1020, 0x0004_4800_0000_0000, u64::MAX),
+    (0xc001_1021, 0x001c_0002_0000_0040, !0x20),
+    (0xc001_1022, 0xc000_0004_0157_0000, u64::MAX),
+    (0xc001_102b, 0x2000_cc10, u64::MAX),
+];
+#[cfg(any(target_os = "linux", test))]
+const RYZEN_19H_ZEN4: MsrPreset = &[
+    (0xc001_1020, 0x0004_4000_0000_0000, u64::MAX),
+    (0xc001_1021, 0x0004_0000_0000_0040, !0x20),
+    (0xc001_1022, 0x8680_0004_0157_0000, u64::MAX),
+    (0xc001_102b, 0x2040_cc10, u64::MAX),
+];
+
+/// The preset XMRig would use for the CPU described by `/proc/cpuinfo`, if it has one.
+#[cfg(any(target_os = "linux", test))]
+fn msr_preset(cpuinfo: &str) -> Option<MsrPreset> {
+    let field = |name: &str| -> Option<&str> {
+        cpuinfo
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.trim() == name)
+            .map(|(_, value)| value.trim())
+    };
+    match field("vendor_id")? {
+        "GenuineIntel" => Some(INTEL),
+        "AuthenticAMD" => {
+            let family: u32 = field("cpu family")?.parse().ok()?;
+            let model: u32 = field("model")?.parse().ok()?;
+            match (family, model) {
+                (0x17, _) => Some(RYZEN_17H),
+                (0x19, 0x10..=0x1f | 0x60..=0x7f) => Some(RYZEN_19H_ZEN4),
+                (0x19, _) => Some(RYZEN_19H),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The bits of `value` selected by `mask`, with the rest of `old` kept.
+#[cfg(any(target_os = "linux", test))]
+fn masked(old: u64, value: u64, mask: u64) -> u64 {
+    (old & !mask) | (value & mask)
+}
+
+/// Reserve huge pages and tune the CPU's model-specific registers for RandomX, as XMRig does when
+/// running as root. Needs root. Returns what was done.
+#[cfg(target_os = "linux")]
+pub fn tune() -> Result<Vec<String>> {
+    let mut done = vec![reserve_huge_pages()?];
+    #[cfg(target_arch = "x86_64")]
+    done.push(tune_msr()?);
+    Ok(done)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn tune() -> Result<Vec<String>> {
+    Err(Error::msg(
+        "Tuning the system for XMRig is only supported on Linux",
+    ))
+}
+
+/// Reserve the huge pages XMRig uses, now and on every boot.
+#[cfg(target_os = "linux")]
+fn reserve_huge_pages() -> Result<String> {
+    const NR_HUGEPAGES: &str = "/proc/sys/vm/nr_hugepages";
+
+    let meminfo = fs::read_to_string("/proc/meminfo")?;
+    let (_, page_size) = crate::preflight::parse_huge_pages(&meminfo)
+        .ok_or_else(|| Error::msg("This kernel doesn't support huge pages"))?;
+    let wanted = HUGE_PAGES_WANTED / page_size;
+    let reserved: u64 = fs::read_to_string(NR_HUGEPAGES)?.trim().parse()?;
+    if reserved >= wanted {
+        return Ok(format!("{} huge pages were already reserved", reserved));
+    }
+
+    fs::write(NR_HUGEPAGES, wanted.to_string())?;
+    if Path::new("/etc/sysctl.d").is_dir() {
+        fs::write(
+            "/etc/sysctl.d/60-ministo.conf",
+            format!("vm.nr_hugepages = {}\n", wanted),
+        )?;
+    }
+    // The kernel reserves fewer if memory is too fragmented.
+    let reserved: u64 = fs::read_to_string(NR_HUGEPAGES)?.trim().parse()?;
+    Ok(format!("Reserved {} of {} huge pages", reserved, wanted))
+}
+
+/// Load the msr kernel module, now and on every boot, and apply XMRig's preset for this CPU until
+/// the next reboot.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+fn tune_msr() -> Result<String> {
+    if !Path::new("/dev/cpu/0/msr").exists() {
+        let status = process::Command::new("modprobe").arg("msr").status()?;
+        if !status.success() {
+            return Err(Error::msg("Failed to load the msr kernel module"));
+        }
+    }
+    if Path::new("/etc/modules-load.d").is_dir() {
+        fs::write("/etc/modules-load.d/ministo.conf", "msr\n")?;
+    }
+
+    let preset = match msr_preset(&fs::read_to_string("/proc/cpuinfo")?) {
+        Some(preset) => preset,
+        None => return Ok("XMRig has no register preset for this CPU".to_string()),
+    };
+    let mut cpus = 0;
+    for entry in fs::read_dir("/dev/cpu")? {
+        let path = entry?.path().join("msr");
+        if !path.exists() {
+            continue;
+        }
+        let msr = OpenOptions::new().read(true).write(true).open(&path)?;
+        for &(register, value, mask) in preset {
+            let mut old = [0; 8];
+            msr.read_exact_at(&mut old, register.into())?;
+            let new = masked(u64::from_ne_bytes(old), value, mask);
+            msr.write_all_at(&new.to_ne_bytes(), register.into())?;
+        }
+        cpus += 1;
+    }
+    fs::write(MSR_TUNED_MARKER, "")?;
+    Ok(format!(
+        "Tuned the registers of {} CPUs until the next reboot",
+        cpus
+    ))
+}
+
+/// Run `ministo --tune-system` as root through `pkexec`, returning what it did.
+#[command(async)]
+pub async fn tune_system() -> Result<Vec<String>, String> {
+    run_tune_system().await.map_err(|e| e.to_string())
+}
+
+async fn run_tune_system() -> Result<Vec<String>> {
+    let exe = current_exe()?;
+    let (mut rx, _child) = Command::new("pkexec")
+        .args([exe.to_string_lossy().as_ref(), "--tune-system"])
+        .spawn()?;
+
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let mut code = None;
+    while let Some(event) = rx.recv().await {
+        match event {
+            CommandEvent::Stdout(line) => stdout.push(line),
+            CommandEvent::Stderr(line) => stderr.push(line),
+            CommandEvent::Terminated(payload) => code = payload.code,
+            _ => {}
+        }
+    }
+    match code {
+        Some(0) => Ok(stdout),
+        _ if stderr.is_empty() => Err(Error::msg(format!(
+            "Tuning the system failed with code {:?}",
+            code
+        ))),
+        _ => Err(Error::msg(stderr.join("\n"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets() {
+        let cpuinfo = |vendor: &str, family: u32, model: u32| {
+            format!(
+                "processor\t: 0\nvendor_id\t: {}\ncpu family\t: {}\nmodel\t\t: {}\n\
+                model name\t: Some CPU\n",
+                vendor, family, model
+            )
+        };
+        assert_eq!(msr_preset(&cpuinfo("GenuineIntel", 6, 158)), Some(INTEL));
+        assert_eq!(
+            msr_preset(&cpuinfo("AuthenticAMD", 0x17, 0x71)),
+            Some(RYZEN_17H)
+        );
+        assert_eq!(
+            msr_preset(&cpuinfo("AuthenticAMD", 0x19, 0x21)),
+            Some(RYZEN_19H)
+        );
+        assert_eq!(
+            msr_preset(&cpuinfo("AuthenticAMD", 0x19, 0x61)),
+            Some(RYZEN_19H_ZEN4)
+        );
+        assert_eq!(msr_preset(&cpuinfo("AuthenticAMD", 0x15, 2)), None);
+        assert_eq!(msr_preset("processor\t: 0\n"), None);
+    }
+
+    #[test]
+    fn keeps_masked_bits() {
+        assert_eq!(masked(0xff, 0x40, !0x20), 0x60);
+        assert_eq!(masked(0xff, 0x0f, u64::MAX), 0x0f);
+    }
+}